@@ -17,22 +17,139 @@
 #[ink_lang::contract]
 pub mod subscrypt {
     use core::convert::TryInto;
-    use ink_env::hash::{HashOutput, Sha2x256};
+    use ink_env::hash::{Blake2x256, HashOutput, Keccak256, Sha2x256};
     use ink_env::Error;
     use ink_prelude::string::String;
     use ink_prelude::vec;
     use ink_prelude::vec::Vec;
     use ink_storage::collections::HashMap;
+    use ink_storage::collections::Vec as StorageVec;
     use ink_storage::traits::{PackedLayout, SpreadLayout};
+    use libsecp256k1_core::curve::{Affine, Field, Jacobian, Scalar, AFFINE_G};
+
+    /// Number of daily escrow buckets that are indexed by each word of `escrow_bitmaps`
+    const BUCKETS_PER_WORD: u64 = 128;
+
+    /// Maximum number of hashing rounds of a `Credential`
+    const MAX_CREDENTIAL_ITERATIONS: u32 = 1000;
+
+    /// Maximum length of the salt of a `Credential`
+    const MAX_SALT_LENGTH: usize = 64;
+
+    /// Number of hashing rounds of the `Credential`s which are built on-chain from a `pass`
+    const PASS_CREDENTIAL_ITERATIONS: u32 = 100;
+
+    /// Maximum number of login keys of each account
+    const MAX_LOGIN_KEYS: usize = 8;
+
+    /// Maximum difference between `issued_at` of a login challenge and the block timestamp
+    const LOGIN_FRESHNESS_WINDOW: u64 = 60 * 5;
+
+    /// Prefix of the signed login message so other signatures of the wallet can't be replayed
+    const LOGIN_MESSAGE_PREFIX: &[u8] = b"SubsCrypt login";
+
+    /// Maximum lifetime of an access token
+    const MAX_ACCESS_TOKEN_LIFETIME: u64 = 60 * 60 * 24;
+
+    /// Maximum number of unexpired access tokens of each user
+    const MAX_ACCESS_TOKENS: usize = 16;
+
+    /// Maximum number of read grants of each user
+    const MAX_READ_GRANTS: usize = 16;
+
+    /// Maximum number of items in each page of paginated queries
+    const MAX_PAGE_LIMIT: u32 = 100;
+
+    /// Maximum number of guardians of each user
+    const MAX_GUARDIANS: usize = 10;
+
+    /// Minimum time between the approval of a recovery and its finalization, so the original
+    /// key has time to cancel it
+    const MIN_RECOVERY_DELAY: u64 = 60 * 60 * 24;
+
+    /// Maximum length of the short fields of a `ProviderProfile` and each of its tags
+    const MAX_PROFILE_FIELD_LENGTH: usize = 128;
+
+    /// Maximum length of the description of a `ProviderProfile`
+    const MAX_PROFILE_DESCRIPTION_LENGTH: usize = 1024;
+
+    /// Maximum number of tags of a `ProviderProfile`
+    const MAX_PROFILE_TAGS: usize = 8;
+
+    /// Number of days that are covered by the expiration tree of plan statistics
+    const STATS_TREE_SIZE: u64 = 1 << 20;
+
+    /// Maximum number of plans of a `Bundle`
+    const MAX_BUNDLE_COMPONENTS: usize = 8;
+
+    /// Default minimum time between scheduling a plan change and applying it
+    const DEFAULT_NOTICE_PERIOD: u64 = 60 * 60 * 24 * 7;
+
+    /// Panic message of the failed money calculations
+    ///
+    /// Messages do not return `ArithmeticError` to the callers, since ink! does not revert the
+    /// storage changes of a message which returns `Err`. The failed calculations panic with this
+    /// message followed by the `ArithmeticError` variant, so the whole call is reverted.
+    const MATH_ERROR: &str = "Arithmetic error in money calculation";
+
+    /// Errors of the checked money calculations of the contract, they are turned into a
+    /// `MATH_ERROR` panic by the messages
+    #[derive(scale::Encode, scale::Decode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum ArithmeticError {
+        Overflow,
+        Underflow,
+        DivisionByZero,
+    }
+
+    /// checked_add : returns `a + b`
+    pub fn checked_add(a: u128, b: u128) -> Result<u128, ArithmeticError> {
+        a.checked_add(b).ok_or(ArithmeticError::Overflow)
+    }
+
+    /// checked_sub : returns `a - b`
+    pub fn checked_sub(a: u128, b: u128) -> Result<u128, ArithmeticError> {
+        a.checked_sub(b).ok_or(ArithmeticError::Underflow)
+    }
+
+    /// checked_mul : returns `a * b`
+    pub fn checked_mul(a: u128, b: u128) -> Result<u128, ArithmeticError> {
+        a.checked_mul(b).ok_or(ArithmeticError::Overflow)
+    }
+
+    /// checked_div : returns `a / b`
+    pub fn checked_div(a: u128, b: u128) -> Result<u128, ArithmeticError> {
+        a.checked_div(b).ok_or(ArithmeticError::DivisionByZero)
+    }
+
+    /// permille_of : returns `amount * permille / 1000`
+    pub fn permille_of(amount: u128, permille: u128) -> Result<u128, ArithmeticError> {
+        checked_div(checked_mul(amount, permille)?, 1000)
+    }
+
+    /// checked_add_time : returns `a + b` for timestamps and durations
+    pub fn checked_add_time(a: u64, b: u64) -> Result<u64, ArithmeticError> {
+        a.checked_add(b).ok_or(ArithmeticError::Overflow)
+    }
+
+    /// checked_sub_time : returns `a - b` for timestamps and durations
+    pub fn checked_sub_time(a: u64, b: u64) -> Result<u64, ArithmeticError> {
+        a.checked_sub(b).ok_or(ArithmeticError::Underflow)
+    }
+
     /// This struct represents a subscription record
     /// # fields:
     /// * provider
     /// * plan
     /// * plan_index
-    /// * subscription_time : this stores start time of each subscription (used in linkedList)
+    /// * subscription_time : this stores start time of each subscription (used in escrow)
     /// * characteristics_values_encrypted : this is the features that user has chosen for her subscription
+    /// * key_version : version of the encryption key of provider that the values are encrypted with,
+    /// 0 means that the provider had no encryption key
+    /// * plan_version : version of the plan that the user has bought
+    /// * bundle_id : id of the bundle that the plan is bought in, `None` if it's bought alone
     /// * refunded
-    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug)]
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct SubscriptionRecord {
         pub provider: AccountId,
@@ -41,18 +158,21 @@ pub mod subscrypt {
         subscription_time: u64,
         characteristics_values_encrypted: Vec<String>,
         //encrypted Data with public key of provider
+        pub key_version: u32,
+        pub plan_version: u32,
+        pub bundle_id: Option<u32>,
         pub refunded: bool,
     }
 
     /// This struct stores user plan records
     /// # fields:
     /// * subscription_records
-    /// * pass_hash : hash of (token + pass_phrase) for authenticating user without wallet
+    /// * credential : credential of pass_phrase for authenticating user without wallet
     #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct PlanRecord {
         pub subscription_records: Vec<SubscriptionRecord>,
-        pass_hash: [u8; 32],
+        pub(crate) credential: Credential,
     }
 
     /// This struct stores configs of plan which is set by provider
@@ -67,93 +187,739 @@ pub mod subscrypt {
         pub disabled: bool,
     }
 
+    /// This struct represents the share of one payee from the earned money of a provider
+    /// # Note
+    /// `share_permille` is out of 1000
+    #[derive(scale::Encode, scale::Decode, PackedLayout, SpreadLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct PayoutShare {
+        pub account: AccountId,
+        pub share_permille: u128,
+    }
+
     /// This struct represents a provider
     /// # fields:
     /// * plans
     /// * money_address : provider earned money will be sent to this address
     /// * payment_manager : struct for handling refund requests
-    /// * subscrypt_pass_hash : password of provider to login into SubsCrypt Dashboard
+    /// * subscrypt_credential : password of provider to login into SubsCrypt Dashboard
     /// * plans_characteristics : array of key arrays of features of that plan
+    /// * payout_table : payees of provider earned money, if it's empty the whole money goes to `money_address`
+    /// * plans_payout_tables : payout table of each plan, if it's empty the `payout_table` is used
+    /// * operators : accounts that can access the provider data on behalf of the provider
+    /// * encryption_keys : public encryption keys of provider, version of each key is its index + 1
     #[derive(scale::Encode, scale::Decode, PackedLayout, SpreadLayout, Debug)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct Provider {
         pub plans: Vec<PlanConsts>,
         pub(crate) plans_characteristics: Vec<Vec<String>>,
         pub(crate) money_address: AccountId,
-        payment_manager: LinkedList,
-        pub subscrypt_pass_hash: [u8; 32],
+        pub(crate) payment_manager: EscrowIndex,
+        pub subscrypt_credential: Credential,
+        pub(crate) payout_table: Vec<PayoutShare>,
+        pub(crate) plans_payout_tables: Vec<Vec<PayoutShare>>,
+        pub(crate) operators: Vec<AccountId>,
+        pub(crate) encryption_keys: Vec<Vec<u8>>,
     }
 
     /// This struct represents a user
     /// # fields:
     /// * list_of_providers : list of providers that the user subscribed to
-    /// * subscrypt_pass_hash : pass hash for retrieve data in subscrypt user dashboard
+    /// * subscrypt_credential : credential for retrieve data in subscrypt user dashboard
     #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct User {
         pub list_of_providers: Vec<AccountId>,
-        pub subscrypt_pass_hash: [u8; 32],
+        pub subscrypt_credential: Credential,
+    }
+
+    /// Hash functions that can be used for hashing a `Credential`
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub enum HashAlgorithm {
+        Sha2x256,
+        Blake2x256,
+        Keccak256,
+    }
+
+    impl HashAlgorithm {
+        /// hash : returns the 256-bit hash of `input` with this algorithm
+        pub fn hash(&self, input: &[u8]) -> [u8; 32] {
+            let mut output = [0u8; 32];
+            match self {
+                HashAlgorithm::Sha2x256 => ink_env::hash_bytes::<Sha2x256>(input, &mut output),
+                HashAlgorithm::Blake2x256 => ink_env::hash_bytes::<Blake2x256>(input, &mut output),
+                HashAlgorithm::Keccak256 => ink_env::hash_bytes::<Keccak256>(input, &mut output),
+            }
+            output
+        }
+    }
+
+    /// This struct represents a salted hash of a pass_phrase
+    /// # fields:
+    /// * salt : random bytes of the credential which are hashed along the pass_phrase
+    /// * algorithm : hash function of the credential
+    /// * iterations : number of hashing rounds
+    /// * hash : result of hashing
+    ///
+    /// # Description
+    /// The pass_phrase is first hashed like `get_sha2` and each round hashes
+    /// `salt ++ previous_hash`. So the unsalted `get_sha2` hashes that clients send as `pass` and
+    /// that the contract stored before salted credentials are credentials with zero iterations,
+    /// and `from_pass` upgrades them to salted credentials without knowing the pass_phrase.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct Credential {
+        pub salt: Vec<u8>,
+        pub algorithm: HashAlgorithm,
+        pub iterations: u32,
+        pub hash: [u8; 32],
+    }
+
+    impl Credential {
+        /// from_pass : builds the credential of `pass`, which is the `get_sha2` hash of the
+        /// pass_phrase, by hashing it `PASS_CREDENTIAL_ITERATIONS` rounds with `salt`
+        pub fn from_pass(pass: [u8; 32], salt: Vec<u8>) -> Self {
+            let hash: [u8; 32] = Self::stretch(
+                pass,
+                &salt,
+                HashAlgorithm::Sha2x256,
+                PASS_CREDENTIAL_ITERATIONS,
+            );
+            Self {
+                salt,
+                algorithm: HashAlgorithm::Sha2x256,
+                iterations: PASS_CREDENTIAL_ITERATIONS,
+                hash,
+            }
+        }
+
+        /// derive : calculates the hash of `pass_phrase` with given `salt`, `algorithm` and
+        /// `iterations`
+        pub fn derive(
+            pass_phrase: &String,
+            salt: &[u8],
+            algorithm: HashAlgorithm,
+            iterations: u32,
+        ) -> [u8; 32] {
+            let mut pass = <Sha2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Sha2x256, _>(pass_phrase, &mut pass);
+            Self::stretch(pass, salt, algorithm, iterations)
+        }
+
+        /// stretch : hashes `salt ++ hash` for `iterations` rounds
+        fn stretch(
+            mut hash: [u8; 32],
+            salt: &[u8],
+            algorithm: HashAlgorithm,
+            iterations: u32,
+        ) -> [u8; 32] {
+            let mut input: Vec<u8> = salt.to_vec();
+            for _ in 0..iterations {
+                input.truncate(salt.len());
+                input.extend_from_slice(&hash);
+                hash = algorithm.hash(&input);
+            }
+            hash
+        }
+
+        /// verify : returns true if `pass_phrase` matches this credential
+        pub fn verify(&self, pass_phrase: &String) -> bool {
+            Self::derive(pass_phrase, &self.salt, self.algorithm, self.iterations) == self.hash
+        }
     }
 
     /// Struct for handling payments of refund
     /// # Description
     ///
-    /// This LinkedList is used for keeping tracking of each subscription that will end in some
-    /// specific date in future. We order these subscriptions by their date of expiration, so we
-    /// will be able to easily calculate and handle refund - withdraw methods with a minimum
-    /// transaction fee. Each entity of the linked-list is `PaymentAdmission` struct.
-    #[derive(scale::Encode, scale::Decode, PackedLayout, SpreadLayout, Debug)]
+    /// The locked money of each provider is kept in daily buckets (`daily_locked_amounts`) keyed by
+    /// the day that subscriptions end in. Each bit of a word of `escrow_bitmaps` shows if the bucket of
+    /// one day is not empty, so adding or removing an entry only touches its own bucket and `withdraw`
+    /// can skip `BUCKETS_PER_WORD` empty days with one storage read. `withdraw` walks the buckets from
+    /// `cursor`, and both the processed buckets and the skipped empty words are counted against its
+    /// `max_buckets`, so the cost of each call is bounded no matter how far the next bucket is.
+    /// # fields:
+    /// * cursor : first day that its bucket is not withdrawn yet
+    /// * back : last day that has locked money
+    /// * length : number of non-empty buckets
+    /// * total_locked : sum of money that is currently locked
+    /// * total_withdrawn : sum of locked money that is released to the provider so far
+    /// * total_refunded : sum of locked money that is paid back to users so far
+    #[derive(scale::Encode, scale::Decode, PackedLayout, SpreadLayout, Debug, Default)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct LinkedList {
-        pub head: u64,
+    pub struct EscrowIndex {
+        pub cursor: u64,
         pub back: u64,
         pub length: u128,
+        pub total_locked: u128,
+        pub total_withdrawn: u128,
+        pub total_refunded: u128,
     }
 
-    /// Struct that represents amount of money that can be withdraw after its due date passed.
-    #[derive(scale::Encode, scale::Decode, PackedLayout, SpreadLayout, Debug)]
+    /// This struct represents the escrow balance statement of a provider
+    /// # fields:
+    /// * total_locked : sum of money that is currently locked
+    /// * total_withdrawn : sum of locked money that is released to the provider so far
+    /// * total_refunded : sum of locked money that is paid back to users so far
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct EscrowStatement {
+        pub total_locked: u128,
+        pub total_withdrawn: u128,
+        pub total_refunded: u128,
+    }
+
+    /// This struct represents an access token of a subscriber
+    /// # fields:
+    /// * user : owner of the token
+    /// * provider : the provider that the token is scoped to
+    /// * plan_index : the plan that the token is scoped to
+    /// * expires_at : the token is not valid after this time
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct AccessToken {
+        pub user: AccountId,
+        pub provider: AccountId,
+        pub plan_index: u128,
+        pub expires_at: u64,
+    }
+
+    /// This struct represents read access of `grantee` to subscription records of a user
+    /// # fields:
+    /// * grantee : the account that can read the records
+    /// * provider : records of this provider can be read, `None` means all providers
+    /// * expires_at : the grant is not valid after this time
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct ReadGrant {
+        pub grantee: AccountId,
+        pub provider: Option<AccountId>,
+        pub expires_at: u64,
+    }
+
+    /// This struct represents the recovery configs of a user
+    /// # fields:
+    /// * guardians : accounts that can approve the recovery of the user
+    /// * threshold : number of approvals that are needed for recovery
+    /// * delay : time between the last needed approval and finalizing the recovery
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct RecoveryConfig {
+        pub guardians: Vec<AccountId>,
+        pub threshold: u32,
+        pub delay: u64,
+    }
+
+    /// This struct represents a recovery of a user that is in progress
+    /// # fields:
+    /// * approvals : (guardian, new account) pairs, each guardian approves one new account
+    /// * cancellations : guardians that voted for cancelling the recovery
+    /// * new_account : the account that the user identity will be moved to, it's set when enough
+    /// guardians approved the same account
+    /// * ready_at : the recovery can be finalized after this time, it's set with `new_account`
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct RecoveryRequest {
+        pub approvals: Vec<(AccountId, AccountId)>,
+        pub cancellations: Vec<AccountId>,
+        pub new_account: Option<AccountId>,
+        pub ready_at: Option<u64>,
+    }
+
+    /// Status of a subscription
+    /// # variants:
+    /// * Active
+    /// * Expired
+    /// * Refunded
+    /// * Scheduled : the subscription starts in the future
+    /// * Paused : the subscription is valid but its plan is disabled by provider
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy, PartialEq)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    struct DailyLockedAmount {
-        amount: u128,
-        next_day: u64,
+    pub enum SubscriptionStatus {
+        Active,
+        Expired,
+        Refunded,
+        Scheduled,
+        Paused,
+    }
+
+    /// This struct represents the status of the subscription of a user to a plan
+    /// # fields:
+    /// * status : status of the current subscription period
+    /// * start : start time of the current subscription period
+    /// * end : time that the subscription expires, queued renewals are included
+    /// * queued_renewals : number of renewals that start after the current subscription period
+    /// * next_renewal_price : price of renewing the subscription with the current plan of provider
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct SubscriptionInfo {
+        pub status: SubscriptionStatus,
+        pub start: u64,
+        pub end: u64,
+        pub queued_renewals: u32,
+        pub next_renewal_price: u128,
+    }
+
+    /// This enum represents a user by their wallet address or their username
+    #[derive(scale::Encode, scale::Decode, Debug, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub enum UserIdentifier {
+        Address(AccountId),
+        Username(String),
+    }
+
+    /// This struct stores the public profile of a provider which is shown in marketplaces
+    /// # fields:
+    /// * display_name
+    /// * description
+    /// * website
+    /// * support_contact : email or any other contact of the provider support
+    /// * logo_hash : content hash of the logo image
+    /// * tags : category tags of the provider
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Default)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct ProviderProfile {
+        pub display_name: String,
+        pub description: String,
+        pub website: String,
+        pub support_contact: String,
+        pub logo_hash: [u8; 32],
+        pub tags: Vec<String>,
+    }
+
+    /// This struct represents a version of a plan
+    /// # fields:
+    /// * consts : `PlanConsts` of the plan in this version
+    /// * changed_at : time that this version is created
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct PlanVersion {
+        pub consts: PlanConsts,
+        pub changed_at: u64,
+    }
+
+    /// This struct represents a scheduled change of a plan
+    /// # fields:
+    /// * consts : `PlanConsts` of the plan after the change
+    /// * effective_at : time that the change is applied
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct PlanChange {
+        pub consts: PlanConsts,
+        pub effective_at: u64,
+    }
+
+    /// This struct stores the capacity configs of a plan
+    /// # fields:
+    /// * max_subscribers : maximum number of concurrent subscribers of the plan
+    /// * waitlist_deposit : money that users pay to join the waitlist of the plan
+    /// * claim_window : time that a user has to subscribe after a seat is offered to them
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct PlanCapacity {
+        pub max_subscribers: u128,
+        pub waitlist_deposit: u128,
+        pub claim_window: u64,
+    }
+
+    /// This struct represents a user in the waitlist of a plan
+    /// # fields:
+    /// * user
+    /// * deposit : the paid deposit which is paid back when the user subscribes or leaves
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct WaitlistEntry {
+        pub user: AccountId,
+        pub deposit: u128,
+    }
+
+    /// This struct represents a freed seat of a plan that is offered to a user of the waitlist
+    /// # fields:
+    /// * user
+    /// * deposit : the paid deposit which is paid to provider if the offer expires
+    /// * expires_at : the user has to subscribe before this time
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct SeatOffer {
+        pub user: AccountId,
+        pub deposit: u128,
+        pub expires_at: u64,
+    }
+
+    /// This struct stores the sale window of a plan, `None` means no limit
+    /// # fields:
+    /// * sale_start : users can subscribe from this time
+    /// * sale_end : users can subscribe before this time
+    /// * renewals : if true renewals are limited to the sale window too
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct SaleWindow {
+        pub sale_start: Option<u64>,
+        pub sale_end: Option<u64>,
+        pub renewals: bool,
+    }
+
+    /// Type hint of the value of a plan characteristic
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub enum CharacteristicType {
+        Text,
+        Number,
+        Boolean,
+    }
+
+    /// This struct stores the rules of the value of a plan characteristic
+    /// # fields:
+    /// * value_type : type hint of the value, it's only checked if the provider has no
+    /// encryption key since encrypted values can't be checked
+    /// * required : if true the value can't be empty
+    /// * max_length : maximum length of the plaintext value, `None` means no limit, it's only
+    /// checked if the provider has no encryption key like `value_type`
+    /// * deprecated : if true the characteristic is not collected anymore and its value must be
+    /// empty, the characteristic is kept so that indices of values don't change
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct CharacteristicRules {
+        pub value_type: CharacteristicType,
+        pub required: bool,
+        pub max_length: Option<u32>,
+        pub deprecated: bool,
+    }
+
+    impl Default for CharacteristicRules {
+        fn default() -> Self {
+            Self {
+                value_type: CharacteristicType::Text,
+                required: false,
+                max_length: None,
+                deprecated: false,
+            }
+        }
+    }
+
+    /// This struct represents a characteristic of a plan
+    /// # fields:
+    /// * key
+    /// * rules : rules of the value of the characteristic
+    #[derive(scale::Encode, scale::Decode, Debug, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct Characteristic {
+        pub key: String,
+        pub rules: CharacteristicRules,
+    }
+
+    /// This struct represents a plan that is sold in a bundle
+    /// # fields:
+    /// * provider
+    /// * plan_index
+    /// * share_permille : share of the plan from the price of the bundle, out of 1000
+    /// * approved : if true the provider of the plan has agreed to sell it in the bundle
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct BundleComponent {
+        pub provider: AccountId,
+        pub plan_index: u128,
+        pub share_permille: u128,
+        pub approved: bool,
+    }
+
+    /// This struct stores a bundle of plans of different providers that are sold together
+    /// # fields:
+    /// * creator : the provider that has created the bundle
+    /// * components : plans of the bundle with their agreed revenue split
+    /// * price : price of the whole bundle
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct Bundle {
+        pub creator: AccountId,
+        pub components: Vec<BundleComponent>,
+        pub price: u128,
+    }
+
+    impl Bundle {
+        /// returns true if every provider of the bundle has approved it
+        pub fn is_approved(&self) -> bool {
+            self.components.iter().all(|component| component.approved)
+        }
+    }
+
+    /// This struct represents a plan in the catalog of a provider
+    /// # fields:
+    /// * plan_index
+    /// * consts : `PlanConsts` of the plan
+    /// * characteristics : keys of the characteristics of the plan
+    /// * enabled : false if the plan is disabled by provider
+    /// * sale_window : sale window of the plan if it has any
+    #[derive(scale::Encode, scale::Decode, Debug, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct CatalogEntry {
+        pub plan_index: u128,
+        pub consts: PlanConsts,
+        pub characteristics: Vec<String>,
+        pub enabled: bool,
+        pub sale_window: Option<SaleWindow>,
+    }
+
+    /// This struct represents a row of subscribers list of a provider
+    /// # fields:
+    /// * user
+    /// * plan_index
+    /// * start : start time of the last subscription of user to the plan
+    /// * end : end time of the last subscription of user to the plan
+    /// * status
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct SubscriberRow {
+        pub user: AccountId,
+        pub plan_index: u128,
+        pub start: u64,
+        pub end: u64,
+        pub status: SubscriptionStatus,
+    }
+
+    /// This struct represents the filters of retrieving records of a user, `None` means no filter
+    /// # fields:
+    /// * provider
+    /// * plan_index
+    /// * status
+    /// * from_time : records that end before this time are omitted
+    /// * to_time : records that start after this time are omitted
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct RecordFilter {
+        pub provider: Option<AccountId>,
+        pub plan_index: Option<u128>,
+        pub status: Option<SubscriptionStatus>,
+        pub from_time: Option<u64>,
+        pub to_time: Option<u64>,
+    }
+
+    /// This struct represents the position of a page of records of a user
+    /// # fields:
+    /// * provider_position : index of provider in `list_of_providers` of user
+    /// * record_index : index of record in `subscription_records` of that provider
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct RecordCursor {
+        pub provider_position: u32,
+        pub record_index: u32,
+    }
+
+    /// This struct represents a subscription record without its characteristics values
+    /// # fields:
+    /// * provider
+    /// * plan_index
+    /// * start : start time of the subscription
+    /// * end : end time of the subscription
+    /// * status
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct RecordSummary {
+        pub provider: AccountId,
+        pub plan_index: u128,
+        pub start: u64,
+        pub end: u64,
+        pub status: SubscriptionStatus,
+    }
+
+    /// This struct stores the lifetime counters of a plan
+    /// # fields:
+    /// * subscriptions : number of new subscriptions, renewals are not counted
+    /// * renewals
+    /// * refunds : number of refunds of users and cancels of provider
+    /// * total_revenue : sum of paid prices
+    /// * refunded_volume : sum of money paid back to users
+    #[derive(
+        scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy, Default,
+    )]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct PlanCounters {
+        pub subscriptions: u128,
+        pub renewals: u128,
+        pub refunds: u128,
+        pub total_revenue: u128,
+        pub refunded_volume: u128,
+    }
+
+    /// This struct stores the statistics of a plan in one day
+    /// # fields:
+    /// * subscriptions : number of new subscriptions in the day
+    /// * renewals : number of renewals in the day
+    /// * refunds : number of refunds in the day
+    /// * revenue : sum of paid prices in the day
+    /// * refunded_volume : sum of money paid back to users in the day
+    /// * expirations : number of subscriptions that end in the day without renewal or refund
+    #[derive(
+        scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Debug, Clone, Copy, Default,
+    )]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct PlanDayStats {
+        pub subscriptions: u128,
+        pub renewals: u128,
+        pub refunds: u128,
+        pub revenue: u128,
+        pub refunded_volume: u128,
+        pub expirations: u128,
+    }
+
+    impl PlanDayStats {
+        /// returns true if nothing happened in the day
+        fn is_empty(&self) -> bool {
+            self.subscriptions == 0
+                && self.renewals == 0
+                && self.refunds == 0
+                && self.revenue == 0
+                && self.refunded_volume == 0
+                && self.expirations == 0
+        }
+    }
+
+    /// This struct represents the statistics of a plan
+    /// # fields:
+    /// * subscriptions : number of new subscriptions, renewals are not counted
+    /// * renewals
+    /// * refunds : number of refunds of users and cancels of provider
+    /// * total_revenue : sum of paid prices
+    /// * refunded_volume : sum of money paid back to users
+    /// * active_subscribers : number of subscriptions that are not refunded or expired
+    /// * churn : number of subscriptions that are refunded or expired without renewal
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, std::cmp::PartialEq))]
+    pub struct PlanStats {
+        pub subscriptions: u128,
+        pub renewals: u128,
+        pub refunds: u128,
+        pub total_revenue: u128,
+        pub refunded_volume: u128,
+        pub active_subscribers: u128,
+        pub churn: u128,
     }
 
     pub struct ProcessReturningData {
-        withdrawing_amount: u128,
-        current_linked_list_head: u64,
-        reduced_length: u128,
+        pub withdrawing_amount: u128,
+        pub next_cursor: u64,
+        pub processed_days: Vec<u64>,
+        // (plan_index, amount) of the withdrawing money of each plan
+        pub plan_amounts: Vec<(u128, u128)>,
     }
 
     /// Main struct of contract
     /// # fields:
-    /// * `start_time` : start time of the contract which is used in `LinkedList`
+    /// * `start_time` : start time of the contract which is used in `EscrowIndex`
+    /// * `owner` : the account that has instantiated the contract
     /// * `provider_register_fee`
+    /// * `notice_period` : minimum time between scheduling a plan change and applying it
     /// * `providers` : the hashmap that stores providers data
+    /// * `provider_list` : addresses of providers in the order of their registration
+    /// * `provider_profiles` : the hashmap that stores public profile of each provider
+    /// * `plan_versions` : the hashmap that stores version history of each plan
+    /// * `plan_changes` : the hashmap that stores scheduled change of each plan
+    /// * `plan_capacities` : the hashmap that stores capacity configs of each plan
+    /// * `waitlists` : the hashmap that stores the users that are waiting for a seat of each plan
+    /// * `seat_offers` : the hashmap that stores the freed seats of each plan that are offered
+    /// * `user_waitlists` : the hashmap that stores the plans whose waitlist each user has joined
+    /// * `sale_windows` : the hashmap that stores sale window of each plan
+    /// * `characteristic_rules` : the hashmap that stores rules of characteristics of each plan,
+    /// characteristics without rules have the default rules
+    /// * `bundles` : bundles of plans of different providers, index of each bundle is its id
     /// * `users` : the hashmap that stores users data
-    /// * `daily_locked_amounts` : the hashmap that stores `DailyLockedAmount` data of each day in order
+    /// * `daily_locked_amounts` : the hashmap that stores locked money of each day
+    /// * `daily_plan_locked_amounts` : the hashmap that stores locked money of each plan in each day,
+    /// so the released money of each plan can be paid with its own payout table
+    /// * `escrow_bitmaps` : the hashmap that stores which days of each provider have locked money
     /// * `records` : the hashmap that stores user's subscription records data
     /// * `plan_index_to_record_index` : the hashmap that stores user's last `SubscriptionRecord` index
     /// * `PlanRecord.subscription_records` for each (user, provider, plan_index)
+    /// * `plan_subscribers` : the hashmap that stores every user that has ever subscribed to each
+    /// plan, refunded, cancelled and expired subscribers are kept
+    /// * `provider_subscriptions` : the hashmap that stores every (user, plan_index) that has ever
+    /// subscribed to each provider
     /// * `username_to_address` mapping of Usernames to Addresses
     /// * `address_to_username` mapping of Addresses to Usernames
+    /// * `login_keys` : compressed ECDSA public keys that can sign login challenges of an account
+    /// * `access_tokens` : the hashmap that stores access tokens by `Sha2x256` hash of the token
+    /// * `user_access_tokens` : hashes of access tokens of each user
+    /// * `read_grants` : accounts that each user granted read access to their records
+    /// * `recovery_configs` : the hashmap that stores guardians of each user
+    /// * `recovery_requests` : the hashmap that stores recoveries that are in progress
+    /// * `plan_counters` : the hashmap that stores lifetime counters of each plan
+    /// * `plan_day_stats` : the hashmap that stores daily statistics of each plan
+    /// * `expiration_tree` : fenwick tree of `PlanDayStats.expirations` of each plan, so the
+    /// number of expired subscriptions can be calculated with logarithmic cost
+    /// * `total_escrowed` : sum of locked money of all providers
+    /// * `total_deposits` : sum of waitlist deposits of all users
+    /// * `treasury` : money of the contract itself like register fees and rounding dust of payments
     #[ink(storage)]
     pub struct Subscrypt {
         start_time: u64,
+        owner: AccountId,
         pub provider_register_fee: u128,
+        notice_period: u64,
         // (provider AccountId) -> provider data
         pub providers: HashMap<AccountId, Provider>,
+        // registration order -> provider AccountId
+        provider_list: StorageVec<AccountId>,
+        // provider AccountId -> ProviderProfile struct
+        provider_profiles: HashMap<AccountId, ProviderProfile>,
+        // (provider AccountId, plan_index) -> PlanVersion structs
+        plan_versions: HashMap<(AccountId, u128), Vec<PlanVersion>>,
+        // (provider AccountId, plan_index) -> PlanChange struct
+        plan_changes: HashMap<(AccountId, u128), PlanChange>,
+        // (provider AccountId, plan_index) -> PlanCapacity struct
+        plan_capacities: HashMap<(AccountId, u128), PlanCapacity>,
+        // (provider AccountId, plan_index) -> WaitlistEntry structs in order
+        waitlists: HashMap<(AccountId, u128), Vec<WaitlistEntry>>,
+        // (provider AccountId, plan_index) -> SeatOffer structs
+        seat_offers: HashMap<(AccountId, u128), Vec<SeatOffer>>,
+        // user AccountId -> (provider AccountId, plan_index) of the joined waitlists
+        user_waitlists: HashMap<AccountId, Vec<(AccountId, u128)>>,
+        // (provider AccountId, plan_index) -> SaleWindow struct
+        sale_windows: HashMap<(AccountId, u128), SaleWindow>,
+        // (provider AccountId, plan_index) -> CharacteristicRules structs
+        characteristic_rules: HashMap<(AccountId, u128), Vec<CharacteristicRules>>,
+        // bundle_id -> Bundle struct
+        bundles: StorageVec<Bundle>,
         // (user AccountId) -> user data
         pub users: HashMap<AccountId, User>,
-        // (provider AccountId , day_id) -> payment admission
-        daily_locked_amounts: HashMap<(AccountId, u64), DailyLockedAmount>,
+        // (provider AccountId , day_id) -> locked amount
+        pub(crate) daily_locked_amounts: HashMap<(AccountId, u64), u128>,
+        // (provider AccountId , day_id) -> (plan_index, locked amount) of plans of the day
+        daily_plan_locked_amounts: HashMap<(AccountId, u64), Vec<(u128, u128)>>,
+        // (provider AccountId , day_id / BUCKETS_PER_WORD) -> bitmap of non-empty buckets
+        pub(crate) escrow_bitmaps: HashMap<(AccountId, u64), u128>,
         // (user AccountId, provider AccountId) -> PlanRecord struct
         pub records: HashMap<(AccountId, AccountId), PlanRecord>,
         // (user AccountId, provider AccountId, plan_index) -> index
         plan_index_to_record_index: HashMap<(AccountId, AccountId, u128), u128>,
+        // (provider AccountId, plan_index) -> users that have subscribed to the plan
+        plan_subscribers: HashMap<(AccountId, u128), Vec<AccountId>>,
+        // provider AccountId -> (user AccountId, plan_index) of subscriptions to the provider
+        provider_subscriptions: HashMap<AccountId, Vec<(AccountId, u128)>>,
         // username -> user AccountId
         username_to_address: HashMap<String, AccountId>,
         // user AccountId -> username
         address_to_username: HashMap<AccountId, String>,
+        // AccountId -> registered login keys
+        login_keys: HashMap<AccountId, Vec<Vec<u8>>>,
+        // token hash -> AccessToken struct
+        access_tokens: HashMap<[u8; 32], AccessToken>,
+        // user AccountId -> token hashes
+        user_access_tokens: HashMap<AccountId, Vec<[u8; 32]>>,
+        // user AccountId -> ReadGrant structs
+        read_grants: HashMap<AccountId, Vec<ReadGrant>>,
+        // user AccountId -> RecoveryConfig struct
+        recovery_configs: HashMap<AccountId, RecoveryConfig>,
+        // lost user AccountId -> RecoveryRequest struct
+        recovery_requests: HashMap<AccountId, RecoveryRequest>,
+        // (provider AccountId, plan_index) -> PlanCounters struct
+        plan_counters: HashMap<(AccountId, u128), PlanCounters>,
+        // (provider AccountId, plan_index, day_id) -> PlanDayStats struct
+        plan_day_stats: HashMap<(AccountId, u128, u64), PlanDayStats>,
+        // (provider AccountId, plan_index, node) -> sum of expirations of the node
+        expiration_tree: HashMap<(AccountId, u128, u64), u128>,
+        total_escrowed: u128,
+        total_deposits: u128,
+        treasury: u128,
     }
 
     impl Default for Subscrypt {
@@ -190,6 +956,48 @@ pub mod subscrypt {
         characteristics: Vec<String>,
     }
 
+    #[ink(event)]
+    pub struct ProviderProfileUpdated {
+        #[ink(topic)]
+        provider: AccountId,
+        profile: ProviderProfile,
+    }
+
+    #[ink(event)]
+    pub struct PlanChangeScheduledEvent {
+        #[ink(topic)]
+        provider: AccountId,
+        #[ink(topic)]
+        plan_index: u128,
+        consts: PlanConsts,
+        effective_at: u64,
+    }
+
+    #[ink(event)]
+    pub struct EncryptionKeyPublishedEvent {
+        #[ink(topic)]
+        provider: AccountId,
+        version: u32,
+    }
+
+    #[ink(event)]
+    pub struct RecoveryEvent {
+        #[ink(topic)]
+        lost_account: AccountId,
+        #[ink(topic)]
+        new_account: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ProviderCancelEvent {
+        #[ink(topic)]
+        provider: AccountId,
+        #[ink(topic)]
+        plan_index: u128,
+        user_address: AccountId,
+        refund_amount: u128,
+    }
+
     impl Subscrypt {
         #[ink(constructor)]
         pub fn new() -> Self {
@@ -200,19 +1008,52 @@ pub mod subscrypt {
         pub fn default() -> Self {
             Self {
                 start_time: Self::env().block_timestamp(),
+                owner: Self::env().caller(),
                 provider_register_fee: 100,
+                notice_period: DEFAULT_NOTICE_PERIOD,
                 providers: HashMap::new(),
+                provider_list: StorageVec::new(),
+                provider_profiles: ink_storage::collections::HashMap::new(),
+                plan_versions: ink_storage::collections::HashMap::new(),
+                plan_changes: ink_storage::collections::HashMap::new(),
+                plan_capacities: ink_storage::collections::HashMap::new(),
+                waitlists: ink_storage::collections::HashMap::new(),
+                seat_offers: ink_storage::collections::HashMap::new(),
+                user_waitlists: ink_storage::collections::HashMap::new(),
+                sale_windows: ink_storage::collections::HashMap::new(),
+                characteristic_rules: ink_storage::collections::HashMap::new(),
+                bundles: StorageVec::new(),
                 users: ink_storage::collections::HashMap::new(),
                 daily_locked_amounts: ink_storage::collections::HashMap::new(),
+                daily_plan_locked_amounts: ink_storage::collections::HashMap::new(),
+                escrow_bitmaps: ink_storage::collections::HashMap::new(),
                 records: ink_storage::collections::HashMap::new(),
                 plan_index_to_record_index: ink_storage::collections::HashMap::new(),
+                plan_subscribers: ink_storage::collections::HashMap::new(),
+                provider_subscriptions: ink_storage::collections::HashMap::new(),
                 username_to_address: ink_storage::collections::HashMap::new(),
                 address_to_username: ink_storage::collections::HashMap::new(),
+                login_keys: ink_storage::collections::HashMap::new(),
+                access_tokens: ink_storage::collections::HashMap::new(),
+                user_access_tokens: ink_storage::collections::HashMap::new(),
+                read_grants: ink_storage::collections::HashMap::new(),
+                recovery_configs: ink_storage::collections::HashMap::new(),
+                recovery_requests: ink_storage::collections::HashMap::new(),
+                plan_counters: ink_storage::collections::HashMap::new(),
+                plan_day_stats: ink_storage::collections::HashMap::new(),
+                expiration_tree: ink_storage::collections::HashMap::new(),
+                total_escrowed: 0,
+                total_deposits: 0,
+                treasury: 0,
             }
         }
 
         /// Registering a new `Provider` by paying the required fee amount (`provider_register_fee`)
         ///
+        /// # Note
+        /// `subscrypt_pass_hash` is the `get_sha2` hash of the pass_phrase and it's stored as a
+        /// `Credential` with a random salt
+        ///
         /// # Panics
         /// if length of plan inputs are not the same
         /// If paid amount is less than `provider_register_fee`
@@ -279,11 +1120,21 @@ pub mod subscrypt {
                 plans: Vec::new(),
                 plans_characteristics: Vec::new(),
                 money_address: address,
-                payment_manager: LinkedList::new(),
-                subscrypt_pass_hash,
+                payment_manager: EscrowIndex::new(),
+                subscrypt_credential: Credential::from_pass(
+                    subscrypt_pass_hash,
+                    self.random_salt(caller, Some(caller)),
+                ),
+                payout_table: Vec::new(),
+                plans_payout_tables: Vec::new(),
+                operators: Vec::new(),
+                encryption_keys: Vec::new(),
             };
 
             self.providers.insert(caller, provider);
+            self.provider_list.push(caller);
+            self.treasury =
+                checked_add(self.treasury, self.env().transferred_balance()).expect(MATH_ERROR);
             self.add_plan(
                 durations,
                 prices,
@@ -335,6 +1186,10 @@ pub mod subscrypt {
             let mut index: u128 = provider.plans.len().try_into().unwrap();
 
             for i in 0..durations.len() {
+                assert!(
+                    max_refund_permille_policies[i] <= 1000,
+                    "Refund policy must not be more than 1000"
+                );
                 provider.plans.push(PlanConsts {
                     duration: durations[i],
                     price: prices[i],
@@ -345,8 +1200,20 @@ pub mod subscrypt {
                 provider
                     .plans_characteristics
                     .push(plan_characteristics[i].clone());
+                provider.plans_payout_tables.push(Vec::new());
             }
             for i in 0..durations.len() {
+                self.add_plan_version(
+                    caller,
+                    index,
+                    PlanConsts {
+                        duration: durations[i],
+                        price: prices[i],
+                        max_refund_permille_policy: max_refund_permille_policies[i],
+                        disabled: false,
+                    },
+                    self.env().block_timestamp(),
+                );
                 self.env().emit_event(AddPlanEvent {
                     provider: caller,
                     duration: durations[i],
@@ -362,7 +1229,9 @@ pub mod subscrypt {
         ///
         /// # Note
         ///
-        /// This will not effect the users that subscribed prior to the edition of plan
+        /// This will not effect the users that subscribed prior to the edition of plan. The edited
+        /// plan is added as a new version to the version history of the plan. The `disabled` flag
+        /// of the scheduled change of the plan is updated too, so the change doesn't undo it.
         ///
         /// # Panics
         ///
@@ -381,6 +1250,7 @@ pub mod subscrypt {
         ) {
             let number: usize = plan_index.try_into().unwrap();
             let caller = self.env().caller();
+            self.apply_plan_change(caller, plan_index);
 
             let provider = match self.providers.get_mut(&caller) {
                 Some(x) => x,
@@ -392,10 +1262,19 @@ pub mod subscrypt {
                 None => panic!("please select a valid plan"),
             };
 
+            assert!(
+                max_refund_permille_policies <= 1000,
+                "Refund policy must not be more than 1000"
+            );
             plan.duration = duration;
             plan.price = price;
             plan.max_refund_permille_policy = max_refund_permille_policies;
             plan.disabled = disabled;
+            let consts: PlanConsts = *plan;
+            if let Some(change) = self.plan_changes.get_mut(&(caller, plan_index)) {
+                change.consts.disabled = consts.disabled;
+            }
+            self.add_plan_version(caller, plan_index, consts, self.env().block_timestamp());
         }
 
         /// Adding new characteristics to existing plans
@@ -434,245 +1313,740 @@ pub mod subscrypt {
             }
         }
 
-        /// Disabling previously created plans of the `caller`
+        /// Editing the key and rules of the characteristic at `characteristic_index` of
+        /// `plan_index` of the `caller`, it can be used to rename or deprecate a characteristic
         ///
         /// # Note
         ///
-        /// This will not effect the users that subscribed prior to the edition of plan
+        /// This will not effect the values of users that subscribed prior to the edition
         ///
         /// # Panics
-        ///
+        /// If caller is not `provider`
         /// If `plan_index` is bigger than the length of `plans` of `provider`
+        /// If `characteristic_index` is bigger than the number of characteristics of the plan
         ///
         /// # Examples
-        /// Examples in `change_disable_works` in `tests/test.rs`
+        /// Examples in `characteristic_schema_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn change_disable(&mut self, plan_index: u128) {
-            let caller = self.env().caller();
+        pub fn edit_characteristic(
+            &mut self,
+            plan_index: u128,
+            characteristic_index: u32,
+            key: String,
+            rules: CharacteristicRules,
+        ) {
             let number: usize = plan_index.try_into().unwrap();
-            match self.providers.get_mut(&caller) {
+            let caller = self.env().caller();
+
+            let provider = match self.providers.get_mut(&caller) {
+                Some(x) => x,
+                None => panic!("You should first register in the contract!"),
+            };
+            let keys: &mut Vec<String> = match provider.plans_characteristics.get_mut(number) {
+                Some(x) => x,
+                None => panic!("please select a valid plan"),
+            };
+            let index: usize = characteristic_index as usize;
+            let length: usize = keys.len();
+            match keys.get_mut(index) {
+                Some(old_key) => *old_key = key,
+                None => panic!("please select a valid characteristic"),
+            }
+
+            if !self
+                .characteristic_rules
+                .contains_key(&(caller, plan_index))
+            {
+                self.characteristic_rules
+                    .insert((caller, plan_index), Vec::new());
+            }
+            let plan_rules: &mut Vec<CharacteristicRules> = self
+                .characteristic_rules
+                .get_mut(&(caller, plan_index))
+                .unwrap();
+            if plan_rules.len() < length {
+                plan_rules.resize(length, CharacteristicRules::default());
+            }
+            plan_rules[index] = rules;
+        }
+
+        /// Disabling previously created plans of the `caller`
+        ///
+        /// # Note
+        ///
+        /// This will not effect the users that subscribed prior to the edition of plan. The
+        /// `disabled` flag of the scheduled change of the plan is toggled too, so the change
+        /// doesn't undo it.
+        ///
+        /// # Panics
+        ///
+        /// If `plan_index` is bigger than the length of `plans` of `provider`
+        ///
+        /// # Examples
+        /// Examples in `change_disable_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn change_disable(&mut self, plan_index: u128) {
+            let caller = self.env().caller();
+            self.apply_plan_change(caller, plan_index);
+            let number: usize = plan_index.try_into().unwrap();
+            let consts: PlanConsts = match self.providers.get_mut(&caller) {
                 Some(provider) => {
                     assert!(
                         provider.plans.len() > plan_index.try_into().unwrap(),
                         "please select a valid plan"
                     );
-                    provider.plans[number].disabled = !provider.plans[number].disabled
+                    provider.plans[number].disabled = !provider.plans[number].disabled;
+                    provider.plans[number]
                 }
                 None => panic!("You should first register in the contract!"),
+            };
+            if let Some(change) = self.plan_changes.get_mut(&(caller, plan_index)) {
+                change.consts.disabled = consts.disabled;
             }
+            self.add_plan_version(caller, plan_index, consts, self.env().block_timestamp());
         }
 
-        /// Subscribing to `plan_index` of the `provider_address` with `Sha2x256` hashed `pass` and array of characteristics_values_encrypted
-        ///
-        /// In this function, we will lock (`plan.max_refund_permille_policy` * `transferred_balance`) / 1000
-        /// in the `Linked List` of the contract and will transfer the rest of paid money directly to provider
+        /// Scheduling a change of `plan_index` of the `caller` which is applied at `effective_at`,
+        /// subscriptions and renewals before `effective_at` use the current terms of the plan
         ///
         /// # Note
         ///
-        /// The `subscrypt_pass_hash` will only be set if it's the first subscription of the `caller` to the `SubsCrypt` platform
-        /// `caller` can not subscribe to same `plan_index` of the same `provider_address` but
-        /// it can subscribe to different `plan_index` of same `provider_address` .
-        /// This line of code checks that if you previously subscribed to `provider_address` and if it's the first time
-        /// then `list_of_providers` will be updated.
-        ///  `if !self.records.contains_key(&(caller, provider_address)) `
-        ///
+        /// Each plan can have one scheduled change, so a new change replaces the previous one.
+        /// The change is added as a new version to the version history of the plan when it's
+        /// applied.
         ///
         /// # Panics
-        /// If paid amount is not equal to `price` of the plan
-        /// If plan is `disabled`
-        /// If `caller` is already subscribed to plan
-        /// If `provider` does not exist
-        /// If `plan_index` is bigger than the length of `plans` of `provider_address`
-        /// If 'username' has already been taken
+        ///
+        /// If `plan_index` is bigger than the length of `plans` of `provider`
+        /// If `effective_at` is sooner than `notice_period` from now
         ///
         /// # Emits
-        /// SubscribeEvent
+        /// `PlanChangeScheduledEvent`
         ///
         /// # Examples
-        /// Examples in `subscribe_works` and `subscribe_fails_insufficient_paying` in `tests/test.rs`
-        #[ink(message, payable)]
-        pub fn subscribe(
+        /// Examples in `schedule_plan_change_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn schedule_plan_change(
             &mut self,
-            provider_address: AccountId,
             plan_index: u128,
-            pass: [u8; 32],
-            username: String,
-            characteristics_values_encrypted: Vec<String>,
+            duration: u64,
+            price: u128,
+            max_refund_permille_policy: u128,
+            disabled: bool,
+            effective_at: u64,
         ) {
             let caller: AccountId = self.env().caller();
-            let time: u64 = self.env().block_timestamp();
-
+            let number: usize = plan_index.try_into().unwrap();
+            self.apply_plan_change(caller, plan_index);
+            match self.providers.get(&caller) {
+                Some(provider) => {
+                    assert!(provider.plans.len() > number, "please select a valid plan")
+                }
+                None => panic!("You should first register in the contract!"),
+            }
             assert!(
-                !self.check_subscription(caller, provider_address, plan_index),
-                "You are already subscribed to this plan!"
+                max_refund_permille_policy <= 1000,
+                "Refund policy must not be more than 1000"
             );
-
-            let provider = match self.providers.get(&provider_address) {
-                Some(provider) => provider,
-                None => panic!("Provider not existed in the contract!"),
-            };
-
-            let index: usize = plan_index.try_into().unwrap();
-
             assert!(
-                provider.plans.len() > plan_index.try_into().unwrap(),
-                "Wrong plan index!"
-            );
-
-            let consts: PlanConsts = provider.plans[index];
-            let plan_characteristics: Vec<String> = provider.plans_characteristics[index].clone();
-
-            assert_eq!(
-                characteristics_values_encrypted.len(),
-                plan_characteristics.len(),
-                "invalid characteristic values!"
-            );
-            assert_eq!(
-                consts.price,
-                self.env().transferred_balance(),
-                "You have to pay exact plan price"
+                effective_at
+                    >= checked_add_time(self.env().block_timestamp(), self.notice_period)
+                        .expect(MATH_ERROR),
+                "Effective time is sooner than the notice period"
             );
-            assert!(!consts.disabled, "Plan is currently disabled by provider");
-            let plan_characteristics: Vec<String> = provider.plans_characteristics[index].clone();
-
-            assert_eq!(
-                characteristics_values_encrypted.len(),
-                plan_characteristics.len(),
-                "invalid characteristic values!"
+            let consts: PlanConsts = PlanConsts {
+                duration,
+                price,
+                max_refund_permille_policy,
+                disabled,
+            };
+            self.plan_changes.insert(
+                (caller, plan_index),
+                PlanChange {
+                    consts,
+                    effective_at,
+                },
             );
+            self.env().emit_event(PlanChangeScheduledEvent {
+                provider: caller,
+                plan_index,
+                consts,
+                effective_at,
+            });
+        }
 
-            let mut address_has_not_username: bool = true;
-            if self.address_to_username.contains_key(&caller) {
-                address_has_not_username = false;
-            } else {
-                match self.username_to_address.get(&username) {
-                    Some(_) => panic!("username is invalid!"),
-                    None => {}
-                }
-            }
-
-            let addr: &AccountId = &provider.money_address;
-            // send money to money_address (1000 - plan.max_refund_permille_policy) / 1000;
-            assert_eq!(
-                self.transfer(
-                    *addr,
-                    consts.price * (1000 - consts.max_refund_permille_policy) / 1000
-                ),
-                Ok(())
+        /// Canceling the scheduled change of `plan_index` of the `caller`
+        ///
+        /// # Panics
+        ///
+        /// If the plan doesn't have a pending change
+        ///
+        /// # Examples
+        /// Examples in `schedule_plan_change_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn cancel_plan_change(&mut self, plan_index: u128) {
+            let caller: AccountId = self.env().caller();
+            assert!(
+                self.get_pending_plan_change(caller, plan_index).is_some(),
+                "No pending change for this plan"
             );
+            self.plan_changes.take(&(caller, plan_index));
+        }
 
-            if address_has_not_username {
-                self.address_to_username.insert(caller, username.clone());
-                self.username_to_address.insert(username, caller);
-            }
+        /// Setting the minimum time between scheduling a plan change and applying it
+        ///
+        /// # Panics
+        ///
+        /// If `caller` is not the owner of the contract
+        ///
+        /// # Examples
+        /// Examples in `schedule_plan_change_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn set_notice_period(&mut self, notice_period: u64) {
+            assert_eq!(self.env().caller(), self.owner, "You are not the owner");
+            self.notice_period = notice_period;
+        }
 
-            if !self.users.contains_key(&caller) {
-                self.users.insert(
-                    caller,
-                    User {
-                        list_of_providers: Vec::new(),
-                        subscrypt_pass_hash: pass,
-                    },
-                );
+        /// Setting the capacity of `plan_index` of the `caller`, `None` removes the capacity limit
+        ///
+        /// # Note
+        ///
+        /// The capacity doesn't effect the current subscribers. Removing the capacity limit pays
+        /// back the deposits of the waitlist of the plan.
+        /// Expirations are counted per day, so the seat of an expired subscription is freed at the
+        /// start of the day after the day it expires in. Until then the plan is full for the
+        /// waitlist and new subscribers although `check_subscription` of that subscriber is false.
+        ///
+        /// # Panics
+        ///
+        /// If `plan_index` is bigger than the length of `plans` of `provider`
+        ///
+        /// # Examples
+        /// Examples in `waitlist_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn set_plan_capacity(&mut self, plan_index: u128, capacity: Option<PlanCapacity>) {
+            let caller: AccountId = self.env().caller();
+            let number: usize = plan_index.try_into().unwrap();
+            match self.providers.get(&caller) {
+                Some(provider) => {
+                    assert!(provider.plans.len() > number, "please select a valid plan")
+                }
+                None => panic!("You should first register in the contract!"),
             }
-
-            let characteristics_values: Vec<String> = characteristics_values_encrypted.clone();
-
-            let subscription_record = SubscriptionRecord {
-                provider: provider_address,
-                plan: consts,
-                plan_index,
-                subscription_time: time,
-                characteristics_values_encrypted,
-                refunded: false,
-            };
-
-            if let Some(plan_record) = self.records.get_mut(&(caller, provider_address)) {
-                self.plan_index_to_record_index.insert(
-                    (caller, provider_address, plan_index),
-                    plan_record.subscription_records.len().try_into().unwrap(),
-                );
-
-                plan_record.subscription_records.push(subscription_record);
-            } else {
-                self.users
-                    .get_mut(&caller)
-                    .unwrap()
-                    .list_of_providers
-                    .push(provider_address);
-
-                let plan_record: PlanRecord = PlanRecord {
-                    subscription_records: vec![subscription_record],
-                    pass_hash: pass,
-                };
-
-                self.records.insert((caller, provider_address), plan_record);
-
-                self.plan_index_to_record_index
-                    .insert((caller, provider_address, plan_index), 0);
+            match capacity {
+                Some(capacity) => {
+                    self.plan_capacities.insert((caller, plan_index), capacity);
+                    self.update_waitlist(caller, plan_index);
+                }
+                None => {
+                    self.plan_capacities.take(&(caller, plan_index));
+                    let mut deposits: Vec<(AccountId, u128)> = Vec::new();
+                    if let Some(waitlist) = self.waitlists.take(&(caller, plan_index)) {
+                        deposits.extend(waitlist.iter().map(|entry| (entry.user, entry.deposit)));
+                    }
+                    if let Some(offers) = self.seat_offers.take(&(caller, plan_index)) {
+                        deposits.extend(offers.iter().map(|offer| (offer.user, offer.deposit)));
+                    }
+                    for (user, deposit) in deposits {
+                        self.pay_back_deposit(user, deposit);
+                    }
+                }
             }
-            self.add_entry(
-                provider_address,
-                (time + consts.duration - self.start_time) / 86400,
-                (self.env().transferred_balance() * consts.max_refund_permille_policy) / 1000,
-            );
-
-            
-            self.env().emit_event(SubscribeEvent {
-                provider: provider_address,
-                plan_index,
-                user_address: caller,
-                subscription_time: time,
-                duration: consts.duration,
-                price: consts.price,
-                characteristics: characteristics_values
-            });
         }
 
-        /// Renewing subscription of `plan_index` of the `provider_address` array of characteristics_values_encrypted
-        ///
-        /// In this function, we will first unlock (`plan.max_refund_permille_policy` * `transferred_balance`) / 1000
-        /// in the `Linked List` of the contract and will also send that alongside the ((1000 - `plan.max_refund_permille_policy`) * `transferred_balance`) / 1000
-        /// of new recieved money and send that to provider. And (`plan.max_refund_permille_policy` * `transferred_balance`) / 1000 will be locked in the `Linked List`
+        /// Joining the waitlist of `plan_index` of `provider_address` which has no free seat,
+        /// the `caller` has to pay `waitlist_deposit` of the plan
         ///
         /// # Note
         ///
-        /// The start of that renewed subscription will be at the end of current subscription.
-        /// The current subscription can not be refunded.
-        ///
+        /// When a seat is freed it's offered to the head of the waitlist. The `caller` gets back
+        /// the deposit if they subscribe in the claim window or leave the waitlist, otherwise the
+        /// deposit is paid to the provider.
         ///
         /// # Panics
-        /// If paid amount is not equal to `price` of the plan
-        /// If plan is `disabled`
-        /// If `caller` is not already subscribed to plan
-        /// If `provider` does not exist
-        /// If `plan_index` is bigger than the length of `plans` of `provider_address`
         ///
-        /// # Emits
-        /// SubscribeEvent
+        /// If the plan doesn't have a capacity limit
+        /// If paid amount is not equal to `waitlist_deposit` of the plan
+        /// If `caller` is already subscribed to the plan or is in the waitlist
         ///
         /// # Examples
-        /// Examples in `renew_works` in `tests/test.rs`
+        /// Examples in `waitlist_works` in `tests/test.rs`
         #[ink(message, payable)]
-        pub fn renew(
-            &mut self,
-            provider_address: AccountId,
-            plan_index: u128,
-            new_characteristics_values: Vec<String>,
-        ) {
+        pub fn join_waitlist(&mut self, provider_address: AccountId, plan_index: u128) {
             let caller: AccountId = self.env().caller();
-
-            if !self.plan_index_to_record_index.contains_key(&(
-                caller,
-                provider_address,
-                plan_index,
-            )) {
-                panic!("You should have been subscribed to this plan for renew!");
+            let capacity: PlanCapacity =
+                match self.plan_capacities.get(&(provider_address, plan_index)) {
+                    Some(capacity) => *capacity,
+                    None => panic!("Plan doesn't have a capacity limit"),
+                };
+            assert_eq!(
+                capacity.waitlist_deposit,
+                self.env().transferred_balance(),
+                "You have to pay exact waitlist deposit"
+            );
+            assert!(
+                !self.check_subscription(caller, provider_address, plan_index),
+                "You are already subscribed to this plan!"
+            );
+            self.update_waitlist(provider_address, plan_index);
+            assert!(
+                !self.is_in_waitlist(provider_address, plan_index, caller),
+                "You are already in the waitlist"
+            );
+            if !self.waitlists.contains_key(&(provider_address, plan_index)) {
+                self.waitlists
+                    .insert((provider_address, plan_index), Vec::new());
             }
+            self.waitlists
+                .get_mut(&(provider_address, plan_index))
+                .unwrap()
+                .push(WaitlistEntry {
+                    user: caller,
+                    deposit: capacity.waitlist_deposit,
+                });
+            self.total_deposits =
+                checked_add(self.total_deposits, capacity.waitlist_deposit).expect(MATH_ERROR);
+            self.update_waitlist(provider_address, plan_index);
 
-            let last_index: u128 =
-                match self
-                    .plan_index_to_record_index
+            let mut joined: Vec<(AccountId, u128)> =
+                self.user_waitlists.take(&caller).unwrap_or_default();
+            joined.retain(|(provider, index)| {
+                (*provider, *index) != (provider_address, plan_index)
+                    && self.is_in_waitlist(*provider, *index, caller)
+            });
+            joined.push((provider_address, plan_index));
+            self.user_waitlists.insert(caller, joined);
+        }
+
+        /// Leaving the waitlist of `plan_index` of `provider_address` or rejecting the offered
+        /// seat, the deposit is paid back to the `caller`
+        ///
+        /// # Panics
+        ///
+        /// If `caller` is not in the waitlist
+        ///
+        /// # Examples
+        /// Examples in `waitlist_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn leave_waitlist(&mut self, provider_address: AccountId, plan_index: u128) {
+            let caller: AccountId = self.env().caller();
+            self.update_waitlist(provider_address, plan_index);
+            let deposit: u128 = match self.remove_seat_offer(provider_address, plan_index, caller) {
+                Some(deposit) => deposit,
+                None => {
+                    let waitlist: &mut Vec<WaitlistEntry> =
+                        match self.waitlists.get_mut(&(provider_address, plan_index)) {
+                            Some(waitlist) => waitlist,
+                            None => panic!("You are not in the waitlist"),
+                        };
+                    match waitlist.iter().position(|entry| entry.user == caller) {
+                        Some(position) => waitlist.remove(position).deposit,
+                        None => panic!("You are not in the waitlist"),
+                    }
+                }
+            };
+            self.pay_back_deposit(caller, deposit);
+            self.update_waitlist(provider_address, plan_index);
+        }
+
+        /// Offering the freed seats of `plan_index` of `provider_address` to the waitlist and
+        /// paying the deposits of expired offers to the provider. Anyone can call this function
+        /// since seats are freed by expiry of subscriptions too.
+        ///
+        /// # Examples
+        /// Examples in `waitlist_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn process_waitlist(&mut self, provider_address: AccountId, plan_index: u128) {
+            self.update_waitlist(provider_address, plan_index);
+        }
+
+        /// Setting the sale window of `plan_index` of the `caller`, `None` removes the window
+        ///
+        /// # Note
+        ///
+        /// The sale window is checked in addition to `disabled` of the plan
+        ///
+        /// # Panics
+        ///
+        /// If `plan_index` is bigger than the length of `plans` of `provider`
+        /// If `sale_end` is not after `sale_start`
+        ///
+        /// # Examples
+        /// Examples in `sale_window_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn set_sale_window(&mut self, plan_index: u128, window: Option<SaleWindow>) {
+            let caller: AccountId = self.env().caller();
+            let number: usize = plan_index.try_into().unwrap();
+            match self.providers.get(&caller) {
+                Some(provider) => {
+                    assert!(provider.plans.len() > number, "please select a valid plan")
+                }
+                None => panic!("You should first register in the contract!"),
+            }
+            match window {
+                Some(window) => {
+                    if let (Some(sale_start), Some(sale_end)) = (window.sale_start, window.sale_end)
+                    {
+                        assert!(sale_start < sale_end, "Invalid sale window");
+                    }
+                    self.sale_windows.insert((caller, plan_index), window);
+                }
+                None => {
+                    self.sale_windows.take(&(caller, plan_index));
+                }
+            }
+        }
+
+        /// Creating a bundle of `plans` of different providers that are sold together with `price`
+        ///
+        /// # Note
+        ///
+        /// `shares_permille` are out of 1000 and must sum to 1000, they are the agreed revenue split
+        /// of the bundle price between its plans. The plans of `caller` are approved by creating the
+        /// bundle and the other providers have to approve it with `approve_bundle` before it can be sold.
+        ///
+        /// # Returns
+        /// `bundle_id` of the new bundle is returned
+        ///
+        /// # Panics
+        /// If length of `plans` and `shares_permille` are not the same
+        /// If number of `plans` is less than 2 or more than `MAX_BUNDLE_COMPONENTS`
+        /// If any of `plans` does not exist or is repeated
+        /// If sum of `shares_permille` is not 1000
+        /// If `caller` is not the provider of any of `plans`
+        ///
+        /// # Examples
+        /// Examples in `bundle_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn create_bundle(
+            &mut self,
+            plans: Vec<(AccountId, u128)>,
+            shares_permille: Vec<u128>,
+            price: u128,
+        ) -> u32 {
+            let caller: AccountId = self.env().caller();
+            assert_eq!(plans.len(), shares_permille.len(), "Wrong Number of Args");
+            assert!(
+                plans.len() >= 2 && plans.len() <= MAX_BUNDLE_COMPONENTS,
+                "Invalid number of bundle plans"
+            );
+
+            let mut components: Vec<BundleComponent> = Vec::new();
+            let mut sum: u128 = 0;
+            for (&(provider_address, plan_index), &share_permille) in
+                plans.iter().zip(shares_permille.iter())
+            {
+                let number: usize = plan_index.try_into().unwrap();
+                match self.providers.get(&provider_address) {
+                    Some(provider) => {
+                        assert!(provider.plans.len() > number, "please select a valid plan")
+                    }
+                    None => panic!("provider address is not valid!"),
+                }
+                assert!(
+                    !components
+                        .iter()
+                        .any(|component| component.provider == provider_address
+                            && component.plan_index == plan_index),
+                    "Plan is repeated in the bundle"
+                );
+                sum = checked_add(sum, share_permille).expect(MATH_ERROR);
+                components.push(BundleComponent {
+                    provider: provider_address,
+                    plan_index,
+                    share_permille,
+                    approved: provider_address == caller,
+                });
+            }
+            assert_eq!(sum, 1000, "Sum of shares must be 1000");
+            assert!(
+                components.iter().any(|component| component.approved),
+                "You are not a provider of this bundle"
+            );
+
+            let bundle_id: u32 = self.bundles.len();
+            self.bundles.push(Bundle {
+                creator: caller,
+                components,
+                price,
+            });
+            bundle_id
+        }
+
+        /// Approving the plans of `caller` in the bundle with `bundle_id`, the bundle can be sold
+        /// when every provider of it has approved it
+        ///
+        /// # Panics
+        /// If the bundle does not exist
+        /// If `caller` is not the provider of any plan of the bundle
+        ///
+        /// # Examples
+        /// Examples in `bundle_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn approve_bundle(&mut self, bundle_id: u32) {
+            let caller: AccountId = self.env().caller();
+            let bundle: &mut Bundle = match self.bundles.get_mut(bundle_id) {
+                Some(bundle) => bundle,
+                None => panic!("Bundle doesn't exist"),
+            };
+            let mut is_provider: bool = false;
+            for component in bundle.components.iter_mut() {
+                if component.provider == caller {
+                    component.approved = true;
+                    is_provider = true;
+                }
+            }
+            assert!(is_provider, "You are not a provider of this bundle");
+        }
+
+        /// Setting the payout table of the `caller` which splits its earned money between `accounts`
+        ///
+        /// # Note
+        ///
+        /// `shares_permille` are out of 1000 and must sum to 1000. The rounding dust of each payment
+        /// is paid to the first account of the table. Passing empty vectors will remove the table and
+        /// the whole money will be sent to `money_address` again.
+        ///
+        /// # Panics
+        ///
+        /// If the size of vectors passed to the method are different
+        /// If sum of `shares_permille` is not 1000
+        /// If the caller is not a valid provider.
+        ///
+        /// # Examples
+        /// Examples in `payout_table_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn set_payout_table(&mut self, accounts: Vec<AccountId>, shares_permille: Vec<u128>) {
+            let payout_table = Self::build_payout_table(accounts, shares_permille);
+            match self.providers.get_mut(&self.env().caller()) {
+                Some(provider) => provider.payout_table = payout_table,
+                None => panic!("You should first register in the contract!"),
+            }
+        }
+
+        /// Setting the payout table of `plan_index` of the `caller`, this table is used instead of
+        /// the provider payout table for the payments of this plan
+        ///
+        /// # Note
+        ///
+        /// `shares_permille` are out of 1000 and must sum to 1000. Passing empty vectors will remove
+        /// the table of the plan.
+        ///
+        /// # Panics
+        ///
+        /// If the size of vectors passed to the method are different
+        /// If sum of `shares_permille` is not 1000
+        /// If the caller is not a valid provider.
+        /// If `plan_index` is bigger than the length of `plans` of `provider`
+        ///
+        /// # Examples
+        /// Examples in `payout_table_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn set_plan_payout_table(
+            &mut self,
+            plan_index: u128,
+            accounts: Vec<AccountId>,
+            shares_permille: Vec<u128>,
+        ) {
+            let number: usize = plan_index.try_into().unwrap();
+            let payout_table = Self::build_payout_table(accounts, shares_permille);
+
+            let provider = match self.providers.get_mut(&self.env().caller()) {
+                Some(x) => x,
+                None => panic!("You should first register in the contract!"),
+            };
+
+            match provider.plans_payout_tables.get_mut(number) {
+                Some(x) => *x = payout_table,
+                None => panic!("please select a valid plan"),
+            }
+        }
+
+        /// Subscribing to `plan_index` of the `provider_address` with `pass` and array of characteristics_values_encrypted
+        ///
+        /// In this function, we will lock (`plan.max_refund_permille_policy` * `transferred_balance`) / 1000
+        /// in the escrow of the contract and will transfer the rest of paid money directly to provider
+        ///
+        /// # Note
+        ///
+        /// `pass` is the `get_sha2` hash of the pass_phrase, so the pass_phrase is never sent to the
+        /// contract, and it's stored as a `Credential` with a random salt.
+        /// `key_version` is the version of the provider encryption key that the values are encrypted
+        /// with, which is returned by `get_encryption_key`, and it is 0 if provider has no key.
+        /// The `subscrypt_pass_hash` will only be set if it's the first subscription of the `caller` to the `SubsCrypt` platform
+        /// `caller` can not subscribe to same `plan_index` of the same `provider_address` but
+        /// it can subscribe to different `plan_index` of same `provider_address` .
+        /// This line of code checks that if you previously subscribed to `provider_address` and if it's the first time
+        /// then `list_of_providers` will be updated.
+        ///  `if !self.records.contains_key(&(caller, provider_address)) `
+        ///
+        ///
+        /// # Panics
+        /// If paid amount is not equal to `price` of the plan
+        /// If plan is `disabled`
+        /// If the current time is not in the sale window of the plan
+        /// If `caller` is already subscribed to plan
+        /// If `provider` does not exist
+        /// If `plan_index` is bigger than the length of `plans` of `provider_address`
+        /// If 'username' has already been taken
+        /// If the plan has no free seat and no seat is offered to `caller`
+        /// If `key_version` is not the current key version of provider
+        ///
+        /// # Emits
+        /// SubscribeEvent
+        ///
+        /// # Examples
+        /// Examples in `subscribe_works` and `subscribe_fails_insufficient_paying` in `tests/test.rs`
+        #[ink(message, payable)]
+        pub fn subscribe(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            pass: [u8; 32],
+            username: String,
+            characteristics_values_encrypted: Vec<String>,
+            key_version: u32,
+        ) {
+            let caller: AccountId = self.env().caller();
+            self.create_subscription(
+                provider_address,
+                plan_index,
+                pass,
+                characteristics_values_encrypted,
+                key_version,
+                None,
+            );
+            self.set_username(caller, username);
+        }
+
+        /// Subscribing to every plan of the bundle with `bundle_id` with `pass` like `subscribe` and
+        /// array of characteristics_values_encrypted of each plan of the bundle
+        ///
+        /// # Note
+        ///
+        /// The paid amount is split between the plans of the bundle by their `share_permille` and each
+        /// plan gets its own `SubscriptionRecord` with its portion as the price, so the escrow of each
+        /// plan is locked for its provider like a normal subscription. The rounding dust goes to the
+        /// `treasury`.
+        ///
+        /// # Panics
+        /// If the bundle does not exist
+        /// If any provider of the bundle has not approved it
+        /// If paid amount is not equal to `price` of the bundle
+        /// If length of `characteristics_values_encrypted` is not equal to the number of plans of the bundle
+        /// If length of `key_versions` is not equal to the number of plans of the bundle
+        /// If subscribing to any plan of the bundle panics like `subscribe`
+        ///
+        /// # Emits
+        /// SubscribeEvent for each plan of the bundle
+        ///
+        /// # Examples
+        /// Examples in `bundle_works` and `subscribe_bundle_fails_not_approved` in `tests/test.rs`
+        #[ink(message, payable)]
+        pub fn subscribe_bundle(
+            &mut self,
+            bundle_id: u32,
+            pass: [u8; 32],
+            username: String,
+            characteristics_values_encrypted: Vec<Vec<String>>,
+            key_versions: Vec<u32>,
+        ) {
+            let caller: AccountId = self.env().caller();
+            let bundle: Bundle = match self.bundles.get(bundle_id) {
+                Some(bundle) => bundle.clone(),
+                None => panic!("Bundle doesn't exist"),
+            };
+            assert!(
+                bundle.is_approved(),
+                "Bundle is not approved by all providers"
+            );
+            assert_eq!(
+                bundle.price,
+                self.env().transferred_balance(),
+                "You have to pay exact bundle price"
+            );
+            assert_eq!(
+                characteristics_values_encrypted.len(),
+                bundle.components.len(),
+                "invalid characteristic values!"
+            );
+            assert_eq!(
+                key_versions.len(),
+                bundle.components.len(),
+                "invalid key versions!"
+            );
+
+            let mut dust: u128 = bundle.price;
+            for ((component, values), key_version) in bundle
+                .components
+                .iter()
+                .zip(characteristics_values_encrypted)
+                .zip(key_versions)
+            {
+                let price: u128 =
+                    permille_of(bundle.price, component.share_permille).expect(MATH_ERROR);
+                dust = checked_sub(dust, price).expect(MATH_ERROR);
+                self.create_subscription(
+                    component.provider,
+                    component.plan_index,
+                    pass,
+                    values,
+                    key_version,
+                    Some((bundle_id, price)),
+                );
+            }
+            self.treasury = checked_add(self.treasury, dust).expect(MATH_ERROR);
+            self.set_username(caller, username);
+        }
+
+        /// Renewing subscription of `plan_index` of the `provider_address` array of characteristics_values_encrypted
+        ///
+        /// In this function, we will first unlock (`plan.max_refund_permille_policy` * `transferred_balance`) / 1000
+        /// in the escrow of the contract and will also send that alongside the ((1000 - `plan.max_refund_permille_policy`) * `transferred_balance`) / 1000
+        /// of new recieved money and send that to provider. And (`plan.max_refund_permille_policy` * `transferred_balance`) / 1000 will be locked in the escrow
+        ///
+        /// # Note
+        ///
+        /// The start of that renewed subscription will be at the end of current subscription.
+        /// The current subscription can not be refunded.
+        ///
+        ///
+        /// # Panics
+        /// If paid amount is not equal to `price` of the plan
+        /// If plan is `disabled`
+        /// If the sale window of the plan limits renewals and the current time is not in it
+        /// If `caller` is not already subscribed to plan
+        /// If `provider` does not exist
+        /// If `plan_index` is bigger than the length of `plans` of `provider_address`
+        /// If `key_version` is not the current key version of provider
+        ///
+        /// # Emits
+        /// SubscribeEvent
+        ///
+        /// # Examples
+        /// Examples in `renew_works` in `tests/test.rs`
+        #[ink(message, payable)]
+        pub fn renew(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            new_characteristics_values: Vec<String>,
+            key_version: u32,
+        ) {
+            let caller: AccountId = self.env().caller();
+            self.apply_plan_change(provider_address, plan_index);
+
+            if !self.plan_index_to_record_index.contains_key(&(
+                caller,
+                provider_address,
+                plan_index,
+            )) {
+                panic!("You should have been subscribed to this plan for renew!");
+            }
+
+            let last_index: u128 =
+                match self
+                    .plan_index_to_record_index
                     .get(&(caller, provider_address, plan_index))
                 {
                     Some(index) => *index,
@@ -684,10 +2058,12 @@ pub mod subscrypt {
                 .get(&(caller, provider_address))
                 .unwrap()
                 .subscription_records[number];
+            let start_time: u64 =
+                checked_add_time(record.plan.duration, record.subscription_time).expect(MATH_ERROR);
 
             if record.plan_index != plan_index
                 || record.refunded
-                || record.plan.duration + record.subscription_time < self.env().block_timestamp()
+                || start_time < self.env().block_timestamp()
             {
                 panic!("You should have been subscribed to this plan for renew!");
             }
@@ -695,7 +2071,6 @@ pub mod subscrypt {
                 Some(provider) => provider,
                 None => panic!("Provider not existed in the contract!"),
             };
-            let start_time: u64 = record.plan.duration + record.subscription_time;
 
             let index: usize = plan_index.try_into().unwrap();
             let consts: PlanConsts = provider.plans[index];
@@ -706,6 +2081,15 @@ pub mod subscrypt {
                 "You have to pay exact plan price"
             );
             assert!(!consts.disabled, "Plan is currently disabled by provider");
+            assert!(
+                self.is_in_sale_window(provider_address, plan_index, true),
+                "Plan is not on sale"
+            );
+            assert_eq!(
+                key_version,
+                self.current_key_version(provider_address),
+                "Encryption key version is not current"
+            );
 
             let plan_characteristics: Vec<String> = provider.plans_characteristics[index].clone();
             assert_eq!(
@@ -713,22 +2097,34 @@ pub mod subscrypt {
                 plan_characteristics.len(),
                 "invalid characteristic values!"
             );
+            self.validate_characteristics(
+                provider_address,
+                plan_index,
+                &new_characteristics_values,
+                key_version,
+            );
+
+            let (provider_portion, locked_amount, dust) =
+                Self::split_price(&consts).expect(MATH_ERROR);
+            let promised_amount: u128 =
+                permille_of(record.plan.price, record.plan.max_refund_permille_policy)
+                    .expect(MATH_ERROR);
+            let passed_day_id: u64 = self
+                .end_day_id_of(record.subscription_time, record.plan.duration)
+                .expect(MATH_ERROR);
+            let end_day_id: u64 = self
+                .end_day_id_of(start_time, consts.duration)
+                .expect(MATH_ERROR);
 
-            let addr: &AccountId = &provider.money_address;
-            // send money to money_address (1000 - plan.max_refund_permille_policy) / 1000;
+            // send money to payees of provider (1000 - plan.max_refund_permille_policy) / 1000;
             assert_eq!(
-                self.transfer(
-                    *addr,
-                    consts.price * (1000 - consts.max_refund_permille_policy) / 1000
-                ),
+                self.pay_provider(provider_address, plan_index, provider_portion),
                 Ok(())
             );
-            let promised_amount = record.plan.price * record.plan.max_refund_permille_policy / 1000;
             assert_eq!(
-                self.transfer(provider.money_address, promised_amount),
+                self.pay_provider(provider_address, plan_index, promised_amount),
                 Ok(())
             );
-            let passed_time = record.plan.duration + record.subscription_time - self.start_time;
 
             let subscription_record = SubscriptionRecord {
                 provider: provider_address,
@@ -736,13 +2132,18 @@ pub mod subscrypt {
                 plan_index,
                 subscription_time: start_time,
                 characteristics_values_encrypted: new_characteristics_values.clone(),
+                key_version,
+                plan_version: self.current_plan_version(provider_address, plan_index),
+                bundle_id: None,
                 refunded: false,
             };
 
             self.remove_entry(
                 provider_address,
-                passed_time / 86400,
-                promised_amount / 1000,
+                plan_index,
+                passed_day_id,
+                promised_amount,
+                0,
             );
 
             let plan_record = self.records.get_mut(&(caller, provider_address)).unwrap();
@@ -754,11 +2155,19 @@ pub mod subscrypt {
 
             plan_record.subscription_records.push(subscription_record);
 
-            self.add_entry(
+            self.add_entry(provider_address, plan_index, end_day_id, locked_amount);
+            self.treasury = checked_add(self.treasury, dust).expect(MATH_ERROR);
+            self.update_plan_stats(
                 provider_address,
-                (start_time + consts.duration - self.start_time) / 86400,
-                (self.env().transferred_balance() * consts.max_refund_permille_policy) / 1000,
+                plan_index,
+                PlanDayStats {
+                    renewals: 1,
+                    revenue: consts.price,
+                    ..PlanDayStats::default()
+                },
             );
+            self.remove_expiration(provider_address, plan_index, passed_day_id);
+            self.add_expiration(provider_address, plan_index, end_day_id);
             self.env().emit_event(SubscribeEvent {
                 provider: provider_address,
                 plan_index,
@@ -775,20 +2184,29 @@ pub mod subscrypt {
         /// # Note
         ///
         /// The `subscrypt_pass_hash` will also be set in `subscribe` function in first subscription
+        /// `pass` is the `get_sha2` hash of the pass_phrase and it's stored as a `Credential` with
+        /// a random salt
         ///
         ///
         /// # Panics
         /// If `caller` does not exist in `users`
         #[ink(message)]
         pub fn set_user_subscrypt_pass(&mut self, pass: [u8; 32]) {
-            match self.users.get_mut(&self.env().caller()) {
-                Some(x) => x.subscrypt_pass_hash = pass,
+            let caller: AccountId = self.env().caller();
+            let credential: Credential =
+                Credential::from_pass(pass, self.random_salt(caller, None));
+            match self.users.get_mut(&caller) {
+                Some(x) => x.subscrypt_credential = credential,
                 None => panic!("User doesn't exist!"),
             };
         }
 
         /// Setting the `pass_hash` of caller in specific provider to `pass`
         ///
+        /// # Note
+        /// `pass` is the `get_sha2` hash of the pass_phrase and it's stored as a `Credential` with
+        /// a random salt
+        ///
         /// # Panics
         /// If `caller` does not exist in `users`
         #[ink(message)]
@@ -797,11 +2215,11 @@ pub mod subscrypt {
             provider_address: AccountId,
             pass: [u8; 32],
         ) {
-            match self
-                .records
-                .get_mut(&(self.env().caller(), provider_address))
-            {
-                Some(x) => x.pass_hash = pass,
+            let caller: AccountId = self.env().caller();
+            let credential: Credential =
+                Credential::from_pass(pass, self.random_salt(caller, Some(provider_address)));
+            match self.records.get_mut(&(caller, provider_address)) {
+                Some(x) => x.credential = credential,
                 None => panic!("User doesn't exist!"),
             };
         }
@@ -810,567 +2228,3675 @@ pub mod subscrypt {
         /// # Note
         ///
         /// The `subscrypt_pass_hash` will also be set in `provider_register` function in first subscription
+        /// `pass` is the `get_sha2` hash of the pass_phrase and it's stored as a `Credential` with
+        /// a random salt
         ///
         ///
         /// # Panics
         /// If `caller` does not exist in `providers`
         #[ink(message)]
         pub fn set_provider_subscrypt_pass(&mut self, pass: [u8; 32]) {
-            match self.providers.get_mut(&self.env().caller()) {
-                Some(x) => x.subscrypt_pass_hash = pass,
+            let caller: AccountId = self.env().caller();
+            let credential: Credential =
+                Credential::from_pass(pass, self.random_salt(caller, Some(caller)));
+            match self.providers.get_mut(&caller) {
+                Some(x) => x.subscrypt_credential = credential,
                 None => panic!("User doesn't exist!"),
             };
         }
 
-        /// This function is used when providers want to collect the locked money for refund policy
+        /// Setting the `subscrypt_credential` of caller to a salted credential
         ///
-        /// In this function, we will unlock that money which was locked in `subscribe` function via the
-        /// LinkedList mechanism, so providers can `withdraw` them when the due date passed.
+        /// # Note
         ///
-        /// # Returns
-        /// `paid` amount is returned
+        /// `hash` is calculated off-chain like `Credential::derive` so the pass_phrase is never sent
+        /// to the contract.
         ///
         /// # Panics
-        /// If `provider` does not exist
+        /// If `caller` does not exist in `users`
+        /// If `salt` is empty or longer than `MAX_SALT_LENGTH`
+        /// If `iterations` is zero or more than `MAX_CREDENTIAL_ITERATIONS`
         ///
-        /// # Examples
-        /// Examples in `withdraw_works` and `withdraw_works2` in `tests/test.rs`
+        /// # Example
+        /// Examples in `set_credential_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn withdraw(&mut self) -> u128 {
-            assert!(
-                self.providers.contains_key(&self.env().caller()),
-                "You are not a registered provider"
-            );
-
-            let caller: AccountId = self.env().caller();
-            let t = self.process(caller, self.env().block_timestamp() / 86400);
-            if t.withdrawing_amount > 0 {
-                assert_eq!(self.transfer(caller, t.withdrawing_amount), Ok(()));
-            }
-
-            let linked_list: &mut LinkedList =
-                &mut self.providers.get_mut(&caller).unwrap().payment_manager;
-
-            linked_list.length -= t.reduced_length;
-            linked_list.head = t.current_linked_list_head;
-
-            return t.withdrawing_amount;
+        pub fn set_user_subscrypt_credential(
+            &mut self,
+            salt: Vec<u8>,
+            algorithm: HashAlgorithm,
+            iterations: u32,
+            hash: [u8; 32],
+        ) {
+            let credential: Credential = Self::build_credential(salt, algorithm, iterations, hash);
+            match self.users.get_mut(&self.env().caller()) {
+                Some(x) => x.subscrypt_credential = credential,
+                None => panic!("User doesn't exist!"),
+            };
         }
 
-        /// `users` can use this function to easily refund their subscription as the policy of that
-        /// specific plan was set. The `users` will be paid back at most
-        /// (`plan.max_refund_permille_policy` * `transferred_balance`) / 1000 and it will be linearly
-        /// decreased as time passed and will get to 0. The `provider` will get 0 at least and will linearly
-        /// get more if `user` refund later.
-        ///
-        /// # Returns
-        /// `paid` amount is returned
+        /// Setting the `credential` of caller in specific provider to a salted credential
         ///
         /// # Panics
-        /// If `provider` does not exist
+        /// If `caller` has no record in `provider_address`
+        /// If `salt` is empty or longer than `MAX_SALT_LENGTH`
+        /// If `iterations` is zero or more than `MAX_CREDENTIAL_ITERATIONS`
         ///
-        /// # Examples
-        /// Assume that `plan.max_refund_permille_policy` = 500 and `plan.price` = 100 the duration
-        /// of the plan is a month(30 days month). if `user` refund in first half of the month, then the user will
-        /// be paid 50. if `user` refund in day 20th of month then `user` will be paid 33.33 and `provider`
-        /// will be paid 16.66.
-        /// Other Examples in `refund_works` and `refund_works2` in `tests/test.rs`
+        /// # Example
+        /// Examples in `set_credential_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn refund(&mut self, provider_address: AccountId, plan_index: u128) -> u128 {
-            let caller: AccountId = self.env().caller();
-            let time: u64 = self.env().block_timestamp();
-            assert!(
-                self.check_subscription(caller, provider_address, plan_index),
-                "You are not in this plan or already refunded"
-            );
-
-            let last_index =
-                match self
-                    .plan_index_to_record_index
-                    .get(&(caller, provider_address, plan_index))
-                {
-                    Some(index) => index,
-                    None => panic!("index is not valid!"),
-                };
-
-            let number: usize = (*last_index).try_into().unwrap();
-            let record: &SubscriptionRecord = self
+        pub fn set_credential_for_each_provider(
+            &mut self,
+            provider_address: AccountId,
+            salt: Vec<u8>,
+            algorithm: HashAlgorithm,
+            iterations: u32,
+            hash: [u8; 32],
+        ) {
+            let credential: Credential = Self::build_credential(salt, algorithm, iterations, hash);
+            match self
                 .records
-                .get(&(caller, provider_address))
-                .unwrap()
-                .subscription_records
-                .get(number)
-                .unwrap();
-
-            assert!(time - record.subscription_time < record.plan.duration);
-
-            let promised_amount = record.plan.price * record.plan.max_refund_permille_policy;
-            let price: u64 = (record.plan.price * 1000).try_into().unwrap();
-            let used: u64 = price * (time - record.subscription_time) / record.plan.duration;
-            let mut customer_portion_locked_money: u128 = (price - used).try_into().unwrap();
-
-            if customer_portion_locked_money > promised_amount {
-                // in this case the customer wants to refund very early so he want to get
-                // more than the amount of refund policy, so we can only give back just
-                // max_refund_permille_policy of his/her subscription. Whole locked money will go directly to
-                // account of the customer
-
-                customer_portion_locked_money = promised_amount;
-            } else {
-                // in this case the customer wants to refund, but he/she used most of his subscription time
-                // and now he/she will get portion of locked money, and the provider will get the rest of money
-
-                let provider_portion_locked_money =
-                    (promised_amount - customer_portion_locked_money) / 1000;
-                assert_eq!(
-                    self.transfer(
-                        self.providers.get(&provider_address).unwrap().money_address,
-                        provider_portion_locked_money
-                    ),
-                    Ok(())
-                );
-            }
-            assert_eq!(
-                self.transfer(caller, customer_portion_locked_money / 1000),
-                Ok(())
-            );
-
-            let passed_time = record.plan.duration + record.subscription_time - self.start_time;
-            self.remove_entry(
-                provider_address,
-                passed_time / 86400,
-                promised_amount / 1000,
-            );
-            self.records
-                .get_mut(&(caller, provider_address))
-                .unwrap()
-                .subscription_records
-                .get_mut(number)
-                .unwrap()
-                .refunded = true;
-            customer_portion_locked_money
+                .get_mut(&(self.env().caller(), provider_address))
+            {
+                Some(x) => x.credential = credential,
+                None => panic!("User doesn't exist!"),
+            };
         }
 
-        /// This function returns the withdrawable amount
-        ///
-        /// # Returns
-        /// `paid` amount is returned
+        /// Setting the `subscrypt_credential` of caller provider to a salted credential
         ///
         /// # Panics
-        /// If `provider` does not exist
-        ///
-        /// # Examples
-        /// Examples in `withdraw_works` and `withdraw_works2` in `tests/test.rs`
-        #[ink(message)]
-        pub fn get_withdrawable_amount(&self) -> u128 {
-            assert!(
-                self.providers.contains_key(&self.env().caller()),
-                "You are not a registered provider"
-            );
-
-            let caller: AccountId = self.env().caller();
-            let t = self.process(caller, self.env().block_timestamp() / 86400);
-            t.withdrawing_amount
-        }
-
-        /// This function indicate if `user` can authenticate with given `pass_phrase`
-        /// # Note
-        /// `user` are encouraged to have different `pass_phrase` for each provider
-        ///
-        /// # Returns
-        /// `bool` is returned which shows the correctness of auth
+        /// If `caller` does not exist in `providers`
+        /// If `salt` is empty or longer than `MAX_SALT_LENGTH`
+        /// If `iterations` is zero or more than `MAX_CREDENTIAL_ITERATIONS`
         ///
         /// # Example
-        /// Examples in `check_auth_works` in `tests/test.rs`
+        /// Examples in `set_credential_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn check_auth(
-            &self,
-            user: AccountId,
-            provider: AccountId,
-            pass_phrase: String,
-        ) -> bool {
-            return match self.records.get(&(user, provider)) {
-                Some(record) => {
-                    let encoded = self.env().hash_encoded::<Sha2x256, _>(&pass_phrase);
-                    return encoded == record.pass_hash;
-                }
-                None => false,
+        pub fn set_provider_subscrypt_credential(
+            &mut self,
+            salt: Vec<u8>,
+            algorithm: HashAlgorithm,
+            iterations: u32,
+            hash: [u8; 32],
+        ) {
+            let credential: Credential = Self::build_credential(salt, algorithm, iterations, hash);
+            match self.providers.get_mut(&self.env().caller()) {
+                Some(x) => x.subscrypt_credential = credential,
+                None => panic!("User doesn't exist!"),
             };
         }
 
-        /// This function indicate if `username` can authenticate with given `pass_phrase`
+        /// Registering `key` as a login key of the `caller`, so `key` is accepted in `verify_login`
+        /// for the `caller`
+        ///
         /// # Note
-        /// `user` are encouraged to have different `pass_phrase` for each provider
+        /// `key` is a 33 bytes compressed ECDSA public key
         ///
-        /// # Returns
-        /// `bool` is returned which shows the correctness of auth
+        /// # Panics
+        /// If `key` is not 33 bytes
+        /// If `key` is already registered for the `caller`
+        /// If `caller` has `MAX_LOGIN_KEYS` login keys
         ///
         /// # Example
-        /// Examples in `check_auth_works` in `tests/test.rs`
+        /// Examples in `verify_login_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn check_auth_with_username(
-            &self,
-            username: String,
-            provider: AccountId,
-            pass_phrase: String,
-        ) -> bool {
-            let user = match self.username_to_address.get(&username) {
-                Some(name) => *name,
-                None => panic!("this username is invalid!"),
-            };
-            self.check_auth(user, provider, pass_phrase)
+        pub fn add_login_key(&mut self, key: Vec<u8>) {
+            assert_eq!(key.len(), 33, "Invalid login key");
+            let caller: AccountId = self.env().caller();
+            if !self.login_keys.contains_key(&caller) {
+                self.login_keys.insert(caller, Vec::new());
+            }
+            let keys: &mut Vec<Vec<u8>> = self.login_keys.get_mut(&caller).unwrap();
+            assert!(!keys.contains(&key), "Login key already exists");
+            assert!(keys.len() < MAX_LOGIN_KEYS, "Too many login keys");
+            keys.push(key);
         }
 
-        /// This function indicate if `provider` can authenticate with given `pass_phrase`
+        /// Removing `key` from the login keys of the `caller`
         ///
-        /// # Returns
-        /// `bool` is returned which shows the correctness of auth
-        ///
-        /// # Example
-        /// Examples in `check_auth_works` in `tests/test.rs`
+        /// # Panics
+        /// If `key` is not registered for the `caller`
         #[ink(message)]
-        pub fn provider_check_auth(&self, provider: AccountId, pass_phrase: String) -> bool {
-            return match self.providers.get(&provider) {
-                Some(provider) => {
-                    let encoded = self.env().hash_encoded::<Sha2x256, _>(&pass_phrase);
-                    return encoded == provider.subscrypt_pass_hash;
-                }
-                None => false,
+        pub fn remove_login_key(&mut self, key: Vec<u8>) {
+            let keys: &mut Vec<Vec<u8>> = match self.login_keys.get_mut(&self.env().caller()) {
+                Some(keys) => keys,
+                None => panic!("Login key doesn't exist"),
             };
+            match keys.iter().position(|k| *k == key) {
+                Some(index) => {
+                    keys.swap_remove(index);
+                }
+                None => panic!("Login key doesn't exist"),
+            }
         }
 
-        /// This function indicate if `provider` can authenticate with given `pass_phrase` and `username`
+        /// Setting the public profile of the `caller` provider
         ///
-        /// # Returns
-        /// `bool` is returned which shows the correctness of auth
+        /// # Panics
+        /// If `caller` does not exist in `providers`
+        /// If `display_name`, `website`, `support_contact` or a tag is longer than
+        /// `MAX_PROFILE_FIELD_LENGTH`
+        /// If `description` is longer than `MAX_PROFILE_DESCRIPTION_LENGTH`
+        /// If there are more than `MAX_PROFILE_TAGS` tags
+        ///
+        /// # Emits
+        /// `ProviderProfileUpdated`
         ///
         /// # Example
-        /// Examples in `check_auth_works` in `tests/test.rs`
+        /// Examples in `provider_profile_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn provider_check_auth_with_username(
-            &self,
-            username: String,
-            pass_phrase: String,
-        ) -> bool {
-            let address = match self.username_to_address.get(&username) {
-                Some(name) => *name,
-                None => panic!("this username is invalid!"),
-            };
-            self.provider_check_auth(address, pass_phrase)
+        pub fn set_provider_profile(&mut self, profile: ProviderProfile) {
+            let caller: AccountId = self.env().caller();
+            assert!(
+                self.providers.contains_key(&caller),
+                "You are not a registered provider"
+            );
+            assert!(
+                profile.display_name.len() <= MAX_PROFILE_FIELD_LENGTH
+                    && profile.website.len() <= MAX_PROFILE_FIELD_LENGTH
+                    && profile.support_contact.len() <= MAX_PROFILE_FIELD_LENGTH
+                    && profile.description.len() <= MAX_PROFILE_DESCRIPTION_LENGTH,
+                "Profile field is too long"
+            );
+            assert!(
+                profile.tags.len() <= MAX_PROFILE_TAGS
+                    && profile
+                        .tags
+                        .iter()
+                        .all(|tag| tag.len() <= MAX_PROFILE_FIELD_LENGTH),
+                "Invalid profile tags"
+            );
+            self.provider_profiles.insert(caller, profile.clone());
+            self.env().emit_event(ProviderProfileUpdated {
+                provider: caller,
+                profile,
+            });
         }
 
-        /// This function indicate if `user` can authenticate with given `pass_phrase`
+        /// Publishing `key` as the new public encryption key of the `caller` provider, the
+        /// characteristics values of next subscriptions should be encrypted with this key
         ///
         /// # Returns
-        /// `bool` is returned which shows the correctness of auth
+        /// version of the published key
         ///
-        /// # Example
-        /// Examples in `check_auth_works` in `tests/test.rs`
-        #[ink(message)]
-        pub fn user_check_auth(&self, user: AccountId, pass_phrase: String) -> bool {
-            return match self.users.get(&user) {
-                Some(user) => {
-                    let encoded = self.env().hash_encoded::<Sha2x256, _>(&pass_phrase);
-                    return encoded == user.subscrypt_pass_hash;
-                }
-                None => false,
-            };
-        }
-
-        /// This function indicate if `user` can authenticate with given `pass_phrase` and `username`
+        /// # Panics
+        /// If `caller` does not exist in `providers`
+        /// If `key` is empty
         ///
-        /// # Returns
-        /// `bool` is returned which shows the correctness of auth
+        /// # Emits
+        /// `EncryptionKeyPublishedEvent`
         ///
         /// # Example
-        /// Examples in `check_auth_works` in `tests/test.rs`
+        /// Examples in `encryption_key_rotation_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn user_check_auth_with_username(&self, username: String, pass_phrase: String) -> bool {
-            let address = match self.username_to_address.get(&username) {
-                Some(name) => *name,
-                None => panic!("this username is invalid!"),
+        pub fn publish_encryption_key(&mut self, key: Vec<u8>) -> u32 {
+            let caller: AccountId = self.env().caller();
+            assert!(!key.is_empty(), "Invalid encryption key");
+            let provider: &mut Provider = match self.providers.get_mut(&caller) {
+                Some(provider) => provider,
+                None => panic!("You are not a registered provider"),
             };
-            self.user_check_auth(address, pass_phrase)
+            provider.encryption_keys.push(key);
+            let version: u32 = provider.encryption_keys.len().try_into().unwrap();
+            self.env().emit_event(EncryptionKeyPublishedEvent {
+                provider: caller,
+                version,
+            });
+            version
         }
 
-        /// This function indicate if `username` is available
+        /// `user` can use this function to re-submit the characteristics values of their active
+        /// subscription to `plan_index` of `provider_address` encrypted with the current key of
+        /// provider after a key rotation
         ///
-        /// # Returns
-        /// `bool` is returned which shows the correctness of auth
+        /// # Note
+        /// The values of the renewals which are queued after the active subscription are also
+        /// replaced, since they are encrypted with the same old key
+        ///
+        /// # Panics
+        /// If `caller` doesn't have an active subscription to the plan
+        /// If `key_version` is not the current key version of provider
+        /// If the number of values is not the number of plan characteristics
         ///
         /// # Example
-        /// Examples in `check_auth_works` in `tests/test.rs`
+        /// Examples in `encryption_key_rotation_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn is_username_available(&self, username: String) -> bool {
-            !self.username_to_address.contains_key(&username)
-        }
-
-
-        /// This function returns provider money address
-        #[ink(message)]
-        pub fn get_money_address(&self) -> ink_env::AccountId {
-            let caller = self.env().caller();
-
-            let provider = match self.providers.get(&caller) {
-                Some(x) => x,
-                None => panic!("You should first register in the contract!"),
-            };
-
-            provider.money_address
+        pub fn resubmit_characteristics_values(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            characteristics_values_encrypted: Vec<String>,
+            key_version: u32,
+        ) {
+            let caller: AccountId = self.env().caller();
+            assert!(
+                self.check_subscription(caller, provider_address, plan_index),
+                "You are not subscribed to this plan"
+            );
+            assert_eq!(
+                key_version,
+                self.current_key_version(provider_address),
+                "Encryption key version is not current"
+            );
+            let number: usize = plan_index.try_into().unwrap();
+            assert_eq!(
+                characteristics_values_encrypted.len(),
+                self.providers
+                    .get(&provider_address)
+                    .unwrap()
+                    .plans_characteristics[number]
+                    .len(),
+                "invalid characteristic values!"
+            );
+            self.validate_characteristics(
+                provider_address,
+                plan_index,
+                &characteristics_values_encrypted,
+                key_version,
+            );
+            let time: u64 = self.env().block_timestamp();
+            for record in self
+                .records
+                .get_mut(&(caller, provider_address))
+                .unwrap()
+                .subscription_records
+                .iter_mut()
+                .filter(|record| {
+                    record.plan_index == plan_index
+                        && !record.refunded
+                        && checked_add_time(record.plan.duration, record.subscription_time)
+                            .expect(MATH_ERROR)
+                            >= time
+                })
+            {
+                record.characteristics_values_encrypted = characteristics_values_encrypted.clone();
+                record.key_version = key_version;
+            }
         }
 
-        /// This function indicate `username` of `caller`
+        /// Setting the guardians of the `caller` user which can move the user identity to a new
+        /// account if `threshold` of them approve it, the recovery can be finalized `delay` after
+        /// the approvals and the `caller` or a majority of guardians can cancel it in the meantime
         ///
-        /// # Returns
-        /// `bool` is returned which shows the correctness of auth
+        /// # Note
+        /// Any recovery that is in progress for the `caller` is cancelled
+        ///
+        /// # Panics
+        /// If `caller` does not exist in `users` or is a provider
+        /// If `threshold` is zero or more than the number of guardians
+        /// If guardians are more than `MAX_GUARDIANS`, repeated or include the `caller`
+        /// If `delay` is less than `MIN_RECOVERY_DELAY`
         ///
         /// # Example
-        /// Examples in `check_auth_works` in `tests/test.rs`
+        /// Examples in `recovery_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn get_username(&self) -> String {
+        pub fn set_recovery_config(
+            &mut self,
+            guardians: Vec<AccountId>,
+            threshold: u32,
+            delay: u64,
+        ) {
             let caller: AccountId = self.env().caller();
-            match self.address_to_username.get(&caller) {
-                Some(username) => username.clone(),
-                None => panic!("this address has not a valid associated username!"),
+            assert!(self.users.contains_key(&caller), "User doesn't exist!");
+            assert!(
+                !self.providers.contains_key(&caller),
+                "Provider accounts can't be recovered"
+            );
+            assert!(guardians.len() <= MAX_GUARDIANS, "Too many guardians");
+            assert!(
+                threshold > 0 && threshold as usize <= guardians.len(),
+                "Invalid threshold"
+            );
+            for (i, guardian) in guardians.iter().enumerate() {
+                assert!(
+                    *guardian != caller && !guardians[..i].contains(guardian),
+                    "Invalid guardians"
+                );
             }
+            assert!(delay >= MIN_RECOVERY_DELAY, "Recovery delay is too short");
+            self.recovery_requests.take(&caller);
+            self.recovery_configs.insert(
+                caller,
+                RecoveryConfig {
+                    guardians,
+                    threshold,
+                    delay,
+                },
+            );
         }
 
+        /// A guardian of `lost_account` can use this function to approve moving the identity of
+        /// `lost_account` to `new_account`, the first approval starts the recovery
+        ///
+        /// # Note
+        /// Each guardian approves one account and approving another account moves their approval.
+        /// The recovery is ready to be finalized `delay` after `threshold` guardians approved the
+        /// same account. Replacing an account that is already chosen needs the approval of a
+        /// majority of guardians too, so a lost key can't be replaced by fewer guardians than the
+        /// ones that can cancel it.
+        ///
+        /// # Panics
+        /// If `caller` is not a guardian of `lost_account`
+        /// If `caller` already approved `new_account`
+        /// If `new_account` is already a user or a provider or has a username
+        ///
+        /// # Example
+        /// Examples in `recovery_works` and `recovery_replace_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn get_address_by_username(&self, username: String) -> AccountId {
-            match self.username_to_address.get(&username) {
-                Some(address) => *address,
-                None => panic!("this username has not a valid associated address!"),
+        pub fn approve_recovery(&mut self, lost_account: AccountId, new_account: AccountId) {
+            let caller: AccountId = self.env().caller();
+            let (threshold, majority, delay) = self.recovery_votes_of(lost_account, caller);
+            self.assert_account_is_free(new_account);
+
+            if !self.recovery_requests.contains_key(&lost_account) {
+                self.recovery_requests.insert(
+                    lost_account,
+                    RecoveryRequest {
+                        approvals: Vec::new(),
+                        cancellations: Vec::new(),
+                        new_account: None,
+                        ready_at: None,
+                    },
+                );
+            }
+            let now: u64 = self.env().block_timestamp();
+            let request: &mut RecoveryRequest =
+                self.recovery_requests.get_mut(&lost_account).unwrap();
+            assert!(
+                !request.approvals.contains(&(caller, new_account)),
+                "You already approved this recovery"
+            );
+            request
+                .approvals
+                .retain(|(guardian, _)| *guardian != caller);
+            request.cancellations.retain(|guardian| *guardian != caller);
+            request.approvals.push((caller, new_account));
+            Self::drop_unsupported_recovery(request, threshold);
+
+            let needed: usize = match request.new_account {
+                Some(_) => core::cmp::max(threshold, majority),
+                None => threshold,
+            };
+            if request.new_account != Some(new_account)
+                && Self::recovery_approvals_of(request, new_account) >= needed
+            {
+                request.new_account = Some(new_account);
+                request.ready_at = Some(checked_add_time(now, delay).expect(MATH_ERROR));
             }
         }
 
-        /// `user` can use this function to retrieve her whole subscription history to
-        /// different providers.
+        /// A guardian of `lost_account` can use this function to vote for cancelling its recovery,
+        /// the recovery is cancelled when a majority of guardians voted for it
+        ///
         /// # Note
-        /// `user` has to provide their `pass_phrase` which will be used in SubsCrypt dashboard
+        /// The approval of `caller` is withdrawn by the vote
         ///
-        /// # Returns
-        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        /// # Panics
+        /// If `caller` is not a guardian of `lost_account`
+        /// If there is no recovery in progress for `lost_account`
+        /// If `caller` already voted for cancelling the recovery
         ///
         /// # Example
-        /// Examples in `retrieve_whole_data_with_password_works` in `tests/test.rs`
+        /// Examples in `recovery_replace_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn retrieve_whole_data_with_username(
-            &self,
-            username: String,
-            pass_phrase: String,
-        ) -> Vec<SubscriptionRecord> {
-            let user = match self.username_to_address.get(&username) {
-                Some(name) => *name,
-                None => panic!("this username is invalid!"),
+        pub fn vote_cancel_recovery(&mut self, lost_account: AccountId) {
+            let caller: AccountId = self.env().caller();
+            let (threshold, majority, _) = self.recovery_votes_of(lost_account, caller);
+            let request: &mut RecoveryRequest = match self.recovery_requests.get_mut(&lost_account)
+            {
+                Some(request) => request,
+                None => panic!("No recovery in progress"),
             };
-            let encoded = self.env().hash_encoded::<Sha2x256, _>(&pass_phrase);
-            assert_eq!(
-                encoded,
-                self.users.get(&user).unwrap().subscrypt_pass_hash,
-                "Wrong auth"
+            assert!(
+                !request.cancellations.contains(&caller),
+                "You already voted for cancelling this recovery"
             );
-            self.retrieve_whole_data(user)
+            request
+                .approvals
+                .retain(|(guardian, _)| *guardian != caller);
+            request.cancellations.push(caller);
+            Self::drop_unsupported_recovery(request, threshold);
+            if request.cancellations.len() >= majority {
+                self.recovery_requests.take(&lost_account);
+            }
         }
 
-        /// `user` can use this function to retrieve her whole subscription history to
-        /// different providers with their wallet.
+        /// The `caller` can cancel the recovery of its own account with its original key
         ///
-        /// # Returns
-        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        /// # Panics
+        /// If there is no recovery in progress for the `caller`
         ///
         /// # Example
-        /// Examples in `retrieve_whole_data_with_wallet_works` in `tests/test.rs`
+        /// Examples in `recovery_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn retrieve_whole_data_with_wallet(&self) -> Vec<SubscriptionRecord> {
-            let caller: AccountId = self.env().caller();
-            self.retrieve_whole_data(caller)
+        pub fn cancel_recovery(&mut self) {
+            assert!(
+                self.recovery_requests.take(&self.env().caller()).is_some(),
+                "No recovery in progress"
+            );
         }
 
-        /// `user` can use this function to retrieve her subscriptions history for a specific
-        /// provider.
+        /// Finalizing the recovery of `lost_account` after enough guardians approved it and its
+        /// delay is passed, the username, `User`, every `PlanRecord` and the plan indexes of
+        /// `lost_account` are moved to the new account
         ///
         /// # Note
-        /// `user` has to provide their `pass_phrase` for that provider.
+        /// Escrow of locked money is kept per provider so it doesn't need to be moved. Waitlist
+        /// entries and seat offers are moved with their deposits, or paid back to the new account
+        /// if it's already waiting for the same plan. Access tokens, read grants and login keys of
+        /// `lost_account` are removed because its key may be leaked.
         ///
-        /// # Returns
-        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        /// # Panics
+        /// If there is no recovery in progress for `lost_account`
+        /// If the recovery doesn't have enough approvals or its delay is not passed
+        /// If the new account is already in use
+        ///
+        /// # Emits
+        /// `RecoveryEvent`
         ///
         /// # Example
-        /// Examples in `retrieve_data_with_password_works` in `tests/test.rs`
+        /// Examples in `recovery_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn retrieve_data_with_username(
-            &self,
-            username: String,
-            provider_address: AccountId,
-            pass_phrase: String,
-        ) -> Vec<SubscriptionRecord> {
-            let user = match self.username_to_address.get(&username) {
-                Some(name) => *name,
-                None => panic!("this username is invalid!"),
+        pub fn finalize_recovery(&mut self, lost_account: AccountId) {
+            let request: &RecoveryRequest = match self.recovery_requests.get(&lost_account) {
+                Some(request) => request,
+                None => panic!("No recovery in progress"),
             };
-            let encoded = self.env().hash_encoded::<Sha2x256, _>(&pass_phrase);
-            assert_eq!(
-                encoded,
-                self.records
-                    .get(&(user, provider_address))
-                    .unwrap()
-                    .pass_hash,
-                "Wrong auth"
-            );
-            self.retrieve_data(user, provider_address)
+            let new_account: AccountId = match (request.new_account, request.ready_at) {
+                (Some(new_account), Some(ready_at)) => {
+                    assert!(
+                        ready_at <= self.env().block_timestamp(),
+                        "Recovery is not ready"
+                    );
+                    new_account
+                }
+                _ => panic!("Recovery is not ready"),
+            };
+            self.assert_account_is_free(new_account);
+
+            self.recovery_requests.take(&lost_account);
+            self.migrate_user(lost_account, new_account);
+            self.env().emit_event(RecoveryEvent {
+                lost_account,
+                new_account,
+            });
         }
 
-        /// `user` can use this function to retrieve her subscriptions history for a specific
-        /// provider with her wallet.
+        /// Get the recovery configs of `account`
+        #[ink(message)]
+        pub fn get_recovery_config(&self, account: AccountId) -> Option<RecoveryConfig> {
+            self.recovery_configs.get(&account).cloned()
+        }
+
+        /// Get the recovery of `account` that is in progress
+        #[ink(message)]
+        pub fn get_recovery_request(&self, account: AccountId) -> Option<RecoveryRequest> {
+            self.recovery_requests.get(&account).cloned()
+        }
+
+        /// Adding `operator` to the operators of the `caller` provider, operators can access the
+        /// provider data like its escrow statement on behalf of the provider
+        ///
+        /// # Panics
+        /// If `caller` does not exist in `providers`
+        ///
+        /// # Examples
+        /// Examples in `escrow_statement_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn add_operator(&mut self, operator: AccountId) {
+            match self.providers.get_mut(&self.env().caller()) {
+                Some(provider) => {
+                    if !provider.operators.contains(&operator) {
+                        provider.operators.push(operator);
+                    }
+                }
+                None => panic!("You should first register in the contract!"),
+            };
+        }
+
+        /// Removing `operator` from the operators of the `caller` provider
+        ///
+        /// # Panics
+        /// If `caller` does not exist in `providers`
+        #[ink(message)]
+        pub fn remove_operator(&mut self, operator: AccountId) {
+            match self.providers.get_mut(&self.env().caller()) {
+                Some(provider) => provider.operators.retain(|x| *x != operator),
+                None => panic!("You should first register in the contract!"),
+            };
+        }
+
+        /// This function is used when providers want to collect the locked money for refund policy
+        ///
+        /// In this function, we will unlock that money which was locked in `subscribe` function via the
+        /// `EscrowIndex` mechanism, so providers can `withdraw` them when the due date passed.
+        /// The unlocked money of each plan is paid like its other earned money, so it is split
+        /// between the payees of the payout table of the plan or the provider if they are set.
+        ///
+        /// # Note
+        ///
+        /// At most `max_buckets` days that have locked money are withdrawn in each call, so providers
+        /// with a long history of subscriptions can withdraw their money in several calls. Each empty
+        /// word of the escrow bitmap that is skipped is counted as one bucket too.
         ///
         /// # Returns
-        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        /// `paid` amount is returned
         ///
-        /// # Example
-        /// Examples in `retrieve_data_with_password_works` in `tests/test.rs`
+        /// # Panics
+        /// If `provider` does not exist
+        ///
+        /// # Examples
+        /// Examples in `withdraw_works` and `withdraw_works2` in `tests/test.rs`
         #[ink(message)]
-        pub fn retrieve_data_with_wallet(
-            &self,
-            provider_address: AccountId,
-        ) -> Vec<SubscriptionRecord> {
+        pub fn withdraw(&mut self, max_buckets: u128) -> u128 {
+            assert!(
+                self.providers.contains_key(&self.env().caller()),
+                "You are not a registered provider"
+            );
+
             let caller: AccountId = self.env().caller();
-            self.retrieve_data(caller, provider_address)
+            let t = self.release_escrow(
+                caller,
+                self.day_id_of(self.env().block_timestamp()),
+                max_buckets,
+            );
+            for (plan_index, amount) in t.plan_amounts.iter() {
+                assert_eq!(self.pay_provider(caller, *plan_index, *amount), Ok(()));
+            }
+
+            return t.withdrawing_amount;
         }
 
-        /// We can get plan data in this function
+        /// `users` can use this function to easily refund their subscription as the policy of that
+        /// specific plan was set. The `users` will be paid back at most
+        /// (`plan.max_refund_permille_policy` * `transferred_balance`) / 1000 and it will be linearly
+        /// decreased as time passed and will get to 0. The `provider` will get 0 at least and will linearly
+        /// get more if `user` refund later.
         ///
         /// # Returns
-        /// `PlanConsts` is returned
+        /// `paid` amount is returned, it is the amount which is transferred to the `user`
         ///
-        /// # Example
-        /// Examples in `tests/test.rs`
+        /// # Panics
+        /// If `provider` does not exist
+        /// If the plan is bought in a bundle, then `refund_bundle` must be used
+        /// If a money calculation fails with an `ArithmeticError`
+        ///
+        /// # Examples
+        /// Assume that `plan.max_refund_permille_policy` = 500 and `plan.price` = 100 the duration
+        /// of the plan is a month(30 days month). if `user` refund in first half of the month, then the user will
+        /// be paid 50. if `user` refund in day 20th of month then `user` will be paid 33.33 and `provider`
+        /// will be paid 16.66.
+        /// Other Examples in `refund_works` and `refund_works2` in `tests/test.rs`
         #[ink(message)]
-        pub fn get_plan_data(&self, provider_address: AccountId, plan_index: u128) -> PlanConsts {
-            let number: usize = plan_index.try_into().unwrap();
-            match self.providers.get(&provider_address) {
-                Some(provider) => match provider.plans.get(number) {
-                    Some(x) => *x,
-                    None => panic!("please select a valid plan"),
-                },
-                None => panic!("provider address is not valid!"),
-            }
+        pub fn refund(&mut self, provider_address: AccountId, plan_index: u128) -> u128 {
+            let caller: AccountId = self.env().caller();
+            assert!(
+                self.check_subscription(caller, provider_address, plan_index),
+                "You are not in this plan or already refunded"
+            );
+            assert!(
+                self.last_record(caller, provider_address, plan_index)
+                    .bundle_id
+                    .is_none(),
+                "Bundle subscriptions must be refunded with refund_bundle"
+            );
+            let refunded_amount: u128 =
+                self.refund_subscription(caller, provider_address, plan_index);
+            assert_eq!(self.transfer(caller, refunded_amount), Ok(()));
+            refunded_amount
         }
 
-        /// get user's plan characteristics in this function
+        /// `users` can use this function to refund every plan of the bundle with `bundle_id` that they
+        /// have bought together. Each plan is refunded like `refund` with its portion of the bundle
+        /// price as its price.
+        ///
+        /// # Note
+        /// Plans of the bundle that are not subscribed with this bundle anymore, like the refunded,
+        /// expired or renewed plans and the plans that are cancelled by their provider, are skipped
         ///
         /// # Returns
-        /// `user's plan characteristics` is returned
+        /// sum of `paid` amounts of plans is returned
         ///
-        /// # Example
-        /// Examples in `tests/test.rs` in get_user_plan_characteristics_works test
+        /// # Panics
+        /// If the bundle does not exist
+        /// If `caller` has no subscription to the plans of the bundle with this bundle
+        ///
+        /// # Examples
+        /// Examples in `bundle_works` in `tests/test.rs`
         #[ink(message)]
-        pub fn get_user_plan_characteristics(&self, user: AccountId, provider_address: AccountId, plan_index: u128) -> Vec<String>  { // TODO: should add authentication
-            if !self
-                .plan_index_to_record_index
-                .contains_key(&(user, provider_address, plan_index))
-            {
-                panic!("user doesn't have this plan!");
+        pub fn refund_bundle(&mut self, bundle_id: u32) -> u128 {
+            let caller: AccountId = self.env().caller();
+            let bundle: Bundle = match self.bundles.get(bundle_id) {
+                Some(bundle) => bundle.clone(),
+                None => panic!("Bundle doesn't exist"),
+            };
+            let mut refunded_amount: u128 = 0;
+            let mut refunded_plans: usize = 0;
+            for component in bundle.components.iter() {
+                if self.check_subscription(caller, component.provider, component.plan_index)
+                    && self
+                        .last_record(caller, component.provider, component.plan_index)
+                        .bundle_id
+                        == Some(bundle_id)
+                {
+                    let amount: u128 =
+                        self.refund_subscription(caller, component.provider, component.plan_index);
+                    refunded_amount = checked_add(refunded_amount, amount).expect(MATH_ERROR);
+                    refunded_plans += 1;
+                }
             }
-            let last_index: u128 = *self
+            assert!(refunded_plans > 0, "You are not subscribed to this bundle");
+            assert_eq!(self.transfer(caller, refunded_amount), Ok(()));
+            refunded_amount
+        }
+
+        /// `provider` can use this function to cancel the subscription of `user` to its `plan_index`
+        /// and pay back `refund_amount` to the `user`. The refund is paid from the money which was
+        /// locked for that subscription plus any extra amount that `provider` sends alongside the
+        /// call. The rest of that money will be sent to `money_address` of the `provider`.
+        ///
+        /// # Returns
+        /// `refund_amount` is returned
+        ///
+        /// # Panics
+        /// If `caller` is not a registered provider
+        /// If `user` is not in this plan or already refunded
+        /// If `refund_amount` is bigger than locked money plus the paid amount
+        ///
+        /// # Emits
+        /// ProviderCancelEvent
+        ///
+        /// # Examples
+        /// Examples in `provider_cancel_subscription_works` in `tests/test.rs`
+        #[ink(message, payable)]
+        pub fn provider_cancel_subscription(
+            &mut self,
+            user: AccountId,
+            plan_index: u128,
+            refund_amount: u128,
+        ) -> u128 {
+            let caller: AccountId = self.env().caller();
+            assert!(
+                self.providers.contains_key(&caller),
+                "You are not a registered provider"
+            );
+            assert!(
+                self.check_subscription(user, caller, plan_index),
+                "User is not in this plan or already refunded"
+            );
+
+            let last_index: u128 = *self
+                .plan_index_to_record_index
+                .get(&(user, caller, plan_index))
+                .unwrap();
+            let number: usize = last_index.try_into().unwrap();
+            let record: &SubscriptionRecord = &self
+                .records
+                .get(&(user, caller))
+                .unwrap()
+                .subscription_records[number];
+
+            let locked_amount: u128 =
+                permille_of(record.plan.price, record.plan.max_refund_permille_policy)
+                    .expect(MATH_ERROR);
+            let available_amount: u128 =
+                checked_add(locked_amount, self.env().transferred_balance()).expect(MATH_ERROR);
+            assert!(
+                refund_amount <= available_amount,
+                "Refund amount is more than the locked and paid money"
+            );
+            let passed_day_id: u64 = self
+                .end_day_id_of(record.subscription_time, record.plan.duration)
+                .expect(MATH_ERROR);
+
+            if refund_amount > 0 {
+                assert_eq!(self.transfer(user, refund_amount), Ok(()));
+            }
+            if available_amount > refund_amount {
+                let provider_amount: u128 =
+                    checked_sub(available_amount, refund_amount).expect(MATH_ERROR);
+                assert_eq!(
+                    self.pay_provider(caller, plan_index, provider_amount),
+                    Ok(())
+                );
+            }
+
+            self.remove_entry(
+                caller,
+                plan_index,
+                passed_day_id,
+                locked_amount,
+                core::cmp::min(refund_amount, locked_amount),
+            );
+            self.records
+                .get_mut(&(user, caller))
+                .unwrap()
+                .subscription_records
+                .get_mut(number)
+                .unwrap()
+                .refunded = true;
+            self.update_plan_stats(
+                caller,
+                plan_index,
+                PlanDayStats {
+                    refunds: 1,
+                    refunded_volume: refund_amount,
+                    ..PlanDayStats::default()
+                },
+            );
+            self.remove_expiration(caller, plan_index, passed_day_id);
+            self.update_waitlist(caller, plan_index);
+
+            self.env().emit_event(ProviderCancelEvent {
+                provider: caller,
+                plan_index,
+                user_address: user,
+                refund_amount,
+            });
+            refund_amount
+        }
+
+        /// This function returns the amount that `withdraw` with the same `max_buckets` would pay
+        ///
+        /// # Returns
+        /// `paid` amount is returned
+        ///
+        /// # Panics
+        /// If `provider` does not exist
+        ///
+        /// # Examples
+        /// Examples in `withdraw_works` and `withdraw_works2` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_withdrawable_amount(&self, max_buckets: u128) -> u128 {
+            assert!(
+                self.providers.contains_key(&self.env().caller()),
+                "You are not a registered provider"
+            );
+
+            let caller: AccountId = self.env().caller();
+            let t = self.process(
+                caller,
+                self.day_id_of(self.env().block_timestamp()),
+                max_buckets,
+            );
+            t.withdrawing_amount
+        }
+
+        /// This function returns the locked money of `provider_address` in each day from `from_day`
+        /// to `to_day`, so providers can see their future cash flow
+        ///
+        /// # Note
+        /// Day ids are calculated like this : (time - contract start date) / 86400
+        ///
+        /// # Returns
+        /// `Vec<(u64, u128)>` of (day id, locked amount) of days that have locked money is returned
+        ///
+        /// # Panics
+        /// If `provider` does not exist
+        /// If `caller` is not the provider or one of its operators
+        ///
+        /// # Examples
+        /// Examples in `escrow_statement_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_escrow_schedule(
+            &self,
+            provider_address: AccountId,
+            from_day: u64,
+            to_day: u64,
+        ) -> Vec<(u64, u128)> {
+            self.assert_provider_access(provider_address);
+            let escrow_index: &EscrowIndex = &self
+                .providers
+                .get(&provider_address)
+                .unwrap()
+                .payment_manager;
+            let to_day: u64 = core::cmp::min(to_day, escrow_index.back);
+            let mut schedule: Vec<(u64, u128)> = Vec::new();
+            let mut cur_id: u64 = core::cmp::max(from_day, escrow_index.cursor);
+            while cur_id <= to_day {
+                let word_id = cur_id / BUCKETS_PER_WORD;
+                let mut remaining_days: u128 =
+                    match self.escrow_bitmaps.get(&(provider_address, word_id)) {
+                        Some(word) => *word >> (cur_id % BUCKETS_PER_WORD),
+                        None => 0,
+                    };
+                while remaining_days != 0 {
+                    let offset = remaining_days.trailing_zeros();
+                    cur_id += u64::from(offset);
+                    if cur_id > to_day {
+                        return schedule;
+                    }
+                    schedule.push((
+                        cur_id,
+                        *self
+                            .daily_locked_amounts
+                            .get(&(provider_address, cur_id))
+                            .unwrap(),
+                    ));
+                    cur_id += 1;
+                    remaining_days = (remaining_days >> offset) >> 1;
+                }
+                cur_id = (word_id + 1) * BUCKETS_PER_WORD;
+            }
+            schedule
+        }
+
+        /// This function returns the escrow balance statement of `provider_address`
+        ///
+        /// # Returns
+        /// `EscrowStatement` which has total locked, withdrawn and refunded money of the provider
+        ///
+        /// # Panics
+        /// If `provider` does not exist
+        /// If `caller` is not the provider or one of its operators
+        ///
+        /// # Examples
+        /// Examples in `escrow_statement_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_escrow_statement(&self, provider_address: AccountId) -> EscrowStatement {
+            self.assert_provider_access(provider_address);
+            let escrow_index: &EscrowIndex = &self
+                .providers
+                .get(&provider_address)
+                .unwrap()
+                .payment_manager;
+            EscrowStatement {
+                total_locked: escrow_index.total_locked,
+                total_withdrawn: escrow_index.total_withdrawn,
+                total_refunded: escrow_index.total_refunded,
+            }
+        }
+
+        /// `audit_solvency` checks that the contract holds enough balance to cover every locked
+        /// escrow entry and waitlist deposit plus its own treasury
+        ///
+        /// # Returns
+        /// `bool` which is true if `balance >= total_escrowed + total_deposits + treasury`
+        ///
+        /// # Example
+        /// Examples in `audit_solvency_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn audit_solvency(&self) -> bool {
+            match checked_add(self.total_escrowed, self.total_deposits)
+                .and_then(|liabilities| checked_add(liabilities, self.treasury))
+            {
+                Ok(liabilities) => self.env().balance() >= liabilities,
+                Err(_) => false,
+            }
+        }
+
+        /// This function indicate if `user` can authenticate with given `pass_phrase`
+        /// # Note
+        /// `user` are encouraged to have different `pass_phrase` for each provider
+        ///
+        /// # Returns
+        /// `bool` is returned which shows the correctness of auth
+        ///
+        /// # Example
+        /// Examples in `check_auth_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn check_auth(
+            &self,
+            user: AccountId,
+            provider: AccountId,
+            pass_phrase: String,
+        ) -> bool {
+            return match self.records.get(&(user, provider)) {
+                Some(record) => record.credential.verify(&pass_phrase),
+                None => false,
+            };
+        }
+
+        /// This function indicate if `username` can authenticate with given `pass_phrase`
+        /// # Note
+        /// `user` are encouraged to have different `pass_phrase` for each provider
+        ///
+        /// # Returns
+        /// `bool` is returned which shows the correctness of auth
+        ///
+        /// # Example
+        /// Examples in `check_auth_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn check_auth_with_username(
+            &self,
+            username: String,
+            provider: AccountId,
+            pass_phrase: String,
+        ) -> bool {
+            let user = match self.username_to_address.get(&username) {
+                Some(name) => *name,
+                None => panic!("this username is invalid!"),
+            };
+            self.check_auth(user, provider, pass_phrase)
+        }
+
+        /// This function indicate if `provider` can authenticate with given `pass_phrase`
+        ///
+        /// # Returns
+        /// `bool` is returned which shows the correctness of auth
+        ///
+        /// # Example
+        /// Examples in `check_auth_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn provider_check_auth(&self, provider: AccountId, pass_phrase: String) -> bool {
+            return match self.providers.get(&provider) {
+                Some(provider) => provider.subscrypt_credential.verify(&pass_phrase),
+                None => false,
+            };
+        }
+
+        /// This function indicate if `provider` can authenticate with given `pass_phrase` and `username`
+        ///
+        /// # Returns
+        /// `bool` is returned which shows the correctness of auth
+        ///
+        /// # Example
+        /// Examples in `check_auth_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn provider_check_auth_with_username(
+            &self,
+            username: String,
+            pass_phrase: String,
+        ) -> bool {
+            let address = match self.username_to_address.get(&username) {
+                Some(name) => *name,
+                None => panic!("this username is invalid!"),
+            };
+            self.provider_check_auth(address, pass_phrase)
+        }
+
+        /// This function indicate if `user` can authenticate with given `pass_phrase`
+        ///
+        /// # Returns
+        /// `bool` is returned which shows the correctness of auth
+        ///
+        /// # Example
+        /// Examples in `check_auth_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn user_check_auth(&self, user: AccountId, pass_phrase: String) -> bool {
+            return match self.users.get(&user) {
+                Some(user) => user.subscrypt_credential.verify(&pass_phrase),
+                None => false,
+            };
+        }
+
+        /// This function indicate if `user` can authenticate with given `pass_phrase` and `username`
+        ///
+        /// # Returns
+        /// `bool` is returned which shows the correctness of auth
+        ///
+        /// # Example
+        /// Examples in `check_auth_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn user_check_auth_with_username(&self, username: String, pass_phrase: String) -> bool {
+            let address = match self.username_to_address.get(&username) {
+                Some(name) => *name,
+                None => panic!("this username is invalid!"),
+            };
+            self.user_check_auth(address, pass_phrase)
+        }
+
+        /// This function indicate if `signature` is a valid answer of `account` to the login
+        /// challenge `nonce` which is issued by a dashboard at `issued_at`
+        ///
+        /// # Note
+        /// The signed message is `get_login_message_hash(account, nonce, issued_at)` and it can be
+        /// signed by the wallet key of `account` or one of its login keys, so no pass_phrase is
+        /// sent to the contract. The signer is recovered from `signature` inside the contract.
+        ///
+        /// The contract doesn't record the nonces, so a signature can be replayed as long as
+        /// `issued_at` is in `LOGIN_FRESHNESS_WINDOW`. The dashboard has to issue each nonce once
+        /// and reject the nonces that are already answered.
+        ///
+        /// # Returns
+        /// `bool` is returned which is false if `account` is not registered, `issued_at` is not
+        /// in `LOGIN_FRESHNESS_WINDOW` of the block timestamp, `signature` is not 65 bytes or its
+        /// signer is not the wallet key or a login key of `account`
+        ///
+        /// # Example
+        /// Examples in `verify_login_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn verify_login(
+            &self,
+            account: AccountId,
+            nonce: [u8; 32],
+            issued_at: u64,
+            signature: Vec<u8>,
+        ) -> bool {
+            if !self.users.contains_key(&account) && !self.providers.contains_key(&account) {
+                return false;
+            }
+            let now: u64 = self.env().block_timestamp();
+            if now.abs_diff(issued_at) > LOGIN_FRESHNESS_WINDOW {
+                return false;
+            }
+            let signature: [u8; 65] = match signature.as_slice().try_into() {
+                Ok(signature) => signature,
+                Err(_) => return false,
+            };
+            let message_hash: [u8; 32] = self.get_login_message_hash(account, nonce, issued_at);
+            let public_key: [u8; 33] = match Self::ecdsa_recover(&signature, &message_hash) {
+                Some(public_key) => public_key,
+                None => return false,
+            };
+
+            let mut key_hash = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_bytes::<Blake2x256>(&public_key, &mut key_hash);
+            if AccountId::from(key_hash) == account {
+                return true;
+            }
+            match self.login_keys.get(&account) {
+                Some(keys) => keys.iter().any(|key| key.as_slice() == &public_key[..]),
+                None => false,
+            }
+        }
+
+        /// This function indicate if `username` is available
+        ///
+        /// # Returns
+        /// `bool` is returned which shows the correctness of auth
+        ///
+        /// # Example
+        /// Examples in `check_auth_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn is_username_available(&self, username: String) -> bool {
+            !self.username_to_address.contains_key(&username)
+        }
+
+
+        /// This function returns provider money address
+        #[ink(message)]
+        pub fn get_money_address(&self) -> ink_env::AccountId {
+            let caller = self.env().caller();
+
+            let provider = match self.providers.get(&caller) {
+                Some(x) => x,
+                None => panic!("You should first register in the contract!"),
+            };
+
+            provider.money_address
+        }
+
+        /// This function indicate `username` of `caller`
+        ///
+        /// # Returns
+        /// `bool` is returned which shows the correctness of auth
+        ///
+        /// # Example
+        /// Examples in `check_auth_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_username(&self) -> String {
+            let caller: AccountId = self.env().caller();
+            match self.address_to_username.get(&caller) {
+                Some(username) => username.clone(),
+                None => panic!("this address has not a valid associated username!"),
+            }
+        }
+
+        #[ink(message)]
+        pub fn get_address_by_username(&self, username: String) -> AccountId {
+            match self.username_to_address.get(&username) {
+                Some(address) => *address,
+                None => panic!("this username has not a valid associated address!"),
+            }
+        }
+
+        /// `user` can use this function to retrieve her whole subscription history to
+        /// different providers.
+        /// # Note
+        /// `user` has to provide their `pass_phrase` which will be used in SubsCrypt dashboard
+        ///
+        /// # Returns
+        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        ///
+        /// # Example
+        /// Examples in `retrieve_whole_data_with_password_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn retrieve_whole_data_with_username(
+            &self,
+            username: String,
+            pass_phrase: String,
+        ) -> Vec<SubscriptionRecord> {
+            let user = match self.username_to_address.get(&username) {
+                Some(name) => *name,
+                None => panic!("this username is invalid!"),
+            };
+            assert!(
+                self.users
+                    .get(&user)
+                    .unwrap()
+                    .subscrypt_credential
+                    .verify(&pass_phrase),
+                "Wrong auth"
+            );
+            self.retrieve_whole_data(user)
+        }
+
+        /// `user` can use this function to retrieve her whole subscription history to
+        /// different providers with their wallet.
+        ///
+        /// # Returns
+        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        ///
+        /// # Example
+        /// Examples in `retrieve_whole_data_with_wallet_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn retrieve_whole_data_with_wallet(&self) -> Vec<SubscriptionRecord> {
+            let caller: AccountId = self.env().caller();
+            self.retrieve_whole_data(caller)
+        }
+
+        /// `user` can use this function to retrieve her subscriptions history for a specific
+        /// provider.
+        ///
+        /// # Note
+        /// `user` has to provide their `pass_phrase` for that provider.
+        ///
+        /// # Returns
+        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        ///
+        /// # Example
+        /// Examples in `retrieve_data_with_password_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn retrieve_data_with_username(
+            &self,
+            username: String,
+            provider_address: AccountId,
+            pass_phrase: String,
+        ) -> Vec<SubscriptionRecord> {
+            let user = match self.username_to_address.get(&username) {
+                Some(name) => *name,
+                None => panic!("this username is invalid!"),
+            };
+            assert!(
+                self.records
+                    .get(&(user, provider_address))
+                    .unwrap()
+                    .credential
+                    .verify(&pass_phrase),
+                "Wrong auth"
+            );
+            self.retrieve_data(user, provider_address)
+        }
+
+        /// `user` can use this function to retrieve her subscriptions history for a specific
+        /// provider with her wallet.
+        ///
+        /// # Returns
+        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        ///
+        /// # Example
+        /// Examples in `retrieve_data_with_password_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn retrieve_data_with_wallet(
+            &self,
+            provider_address: AccountId,
+        ) -> Vec<SubscriptionRecord> {
+            let caller: AccountId = self.env().caller();
+            self.retrieve_data(caller, provider_address)
+        }
+
+        /// `user` can use this function to retrieve a page of their subscription history with
+        /// their wallet.
+        ///
+        /// # Note
+        /// At most `limit` records are visited from `cursor` in the order of `list_of_providers`
+        /// and only the ones matching `filter` are returned, so a page may have less than `limit`
+        /// records
+        ///
+        /// # Returns
+        /// `(Vec<SubscriptionRecord>, Option<RecordCursor>)` is returned which is the records and
+        /// the cursor of the next page, the cursor is `None` if there is no more records
+        ///
+        /// # Panics
+        /// If `limit` is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `retrieve_records_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn retrieve_records_with_wallet(
+            &self,
+            filter: RecordFilter,
+            cursor: RecordCursor,
+            limit: u32,
+        ) -> (Vec<SubscriptionRecord>, Option<RecordCursor>) {
+            let caller: AccountId = self.env().caller();
+            let (records, next_cursor) = self.filter_records(caller, filter, cursor, limit);
+            (records.into_iter().cloned().collect(), next_cursor)
+        }
+
+        /// `user` can use this function to retrieve a page of their subscription history.
+        ///
+        /// # Note
+        /// `user` has to provide their `pass_phrase` which will be used in SubsCrypt dashboard
+        ///
+        /// # Returns
+        /// `(Vec<SubscriptionRecord>, Option<RecordCursor>)` is returned which is the records and
+        /// the cursor of the next page, the cursor is `None` if there is no more records
+        ///
+        /// # Panics
+        /// If `limit` is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `retrieve_records_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn retrieve_records_with_username(
+            &self,
+            username: String,
+            pass_phrase: String,
+            filter: RecordFilter,
+            cursor: RecordCursor,
+            limit: u32,
+        ) -> (Vec<SubscriptionRecord>, Option<RecordCursor>) {
+            let user = match self.username_to_address.get(&username) {
+                Some(name) => *name,
+                None => panic!("this username is invalid!"),
+            };
+            assert!(
+                self.users
+                    .get(&user)
+                    .unwrap()
+                    .subscrypt_credential
+                    .verify(&pass_phrase),
+                "Wrong auth"
+            );
+            let (records, next_cursor) = self.filter_records(user, filter, cursor, limit);
+            (records.into_iter().cloned().collect(), next_cursor)
+        }
+
+        /// `user` can use this function to retrieve a page of the summaries of their
+        /// subscription history with their wallet, the summaries don't have the characteristics
+        /// values.
+        ///
+        /// # Returns
+        /// `(Vec<RecordSummary>, Option<RecordCursor>)` is returned which is the summaries and
+        /// the cursor of the next page, the cursor is `None` if there is no more records
+        ///
+        /// # Panics
+        /// If `limit` is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `retrieve_records_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn retrieve_record_summaries_with_wallet(
+            &self,
+            filter: RecordFilter,
+            cursor: RecordCursor,
+            limit: u32,
+        ) -> (Vec<RecordSummary>, Option<RecordCursor>) {
+            let caller: AccountId = self.env().caller();
+            let (records, next_cursor) = self.filter_records(caller, filter, cursor, limit);
+            let summaries: Vec<RecordSummary> = records
+                .into_iter()
+                .map(|record| RecordSummary {
+                    provider: record.provider,
+                    plan_index: record.plan_index,
+                    start: record.subscription_time,
+                    end: checked_add_time(record.subscription_time, record.plan.duration)
+                        .expect(MATH_ERROR),
+                    status: self.subscription_status_of(record),
+                })
+                .collect();
+            (summaries, next_cursor)
+        }
+
+        /// We can get plan data in this function
+        ///
+        /// # Returns
+        /// `PlanConsts` is returned
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_plan_data(&self, provider_address: AccountId, plan_index: u128) -> PlanConsts {
+            let number: usize = plan_index.try_into().unwrap();
+            match self.providers.get(&provider_address) {
+                Some(provider) => {
+                    assert!(provider.plans.len() > number, "please select a valid plan")
+                }
+                None => panic!("provider address is not valid!"),
+            }
+            self.plan_consts_of(provider_address, plan_index)
+        }
+
+        /// get user's plan characteristics in this function
+        ///
+        /// # Note
+        /// Only `user`, the provider of the plan, its operators and accounts that `user` granted
+        /// read access to can call this function
+        ///
+        /// # Returns
+        /// `user's plan characteristics` is returned
+        ///
+        /// # Panics
+        /// If `caller` doesn't have access to the data
+        /// If `user` doesn't have an active subscription to the plan
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in get_user_plan_characteristics_works test
+        #[ink(message)]
+        pub fn get_user_plan_characteristics(
+            &self,
+            user: AccountId,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> Vec<String> {
+            let caller: AccountId = self.env().caller();
+            assert!(
+                self.is_provider_or_operator(provider_address, caller)
+                    || self.has_read_access(user, provider_address, caller),
+                "You don't have access to this data"
+            );
+            match self.active_record(user, provider_address, plan_index) {
+                Some(record) => record.characteristics_values_encrypted.clone(),
+                None => panic!("user doesn't have this plan!"),
+            }
+        }
+
+        /// get user's plan characteristics in this function with `username` and `pass_phrase` of
+        /// the user for that provider
+        ///
+        /// # Returns
+        /// `user's plan characteristics` is returned
+        ///
+        /// # Panics
+        /// If `username` is not valid or `pass_phrase` is wrong
+        /// If `user` doesn't have an active subscription to the plan
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in get_user_plan_characteristics_works test
+        #[ink(message)]
+        pub fn get_user_plan_characteristics_with_username(
+            &self,
+            username: String,
+            provider_address: AccountId,
+            plan_index: u128,
+            pass_phrase: String,
+        ) -> Vec<String> {
+            let user = match self.username_to_address.get(&username) {
+                Some(name) => *name,
+                None => panic!("this username is invalid!"),
+            };
+            assert!(
+                self.check_auth(user, provider_address, pass_phrase),
+                "Wrong auth"
+            );
+            match self.active_record(user, provider_address, plan_index) {
+                Some(record) => record.characteristics_values_encrypted.clone(),
+                None => panic!("user doesn't have this plan!"),
+            }
+        }
+
+        /// get plan characteristics of a page of subscribers of `plan_index` of `provider_address`
+        ///
+        /// # Note
+        /// The page is `limit` subscribers from `offset` in the order of their first subscription
+        /// and only the subscribers that are active now are returned
+        ///
+        /// # Returns
+        /// `Vec<(AccountId, Vec<String>)>` is returned which is a vector of active subscribers
+        /// and their plan characteristics
+        ///
+        /// # Panics
+        /// If `caller` is not the provider or its operator
+        /// If `limit` is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in get_plan_subscribers_characteristics_works test
+        #[ink(message)]
+        pub fn get_plan_subscribers_characteristics(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            offset: u32,
+            limit: u32,
+        ) -> Vec<(AccountId, Vec<String>)> {
+            self.assert_provider_access(provider_address);
+            assert!(limit <= MAX_PAGE_LIMIT, "Page limit is too big");
+            let mut data: Vec<(AccountId, Vec<String>)> = Vec::new();
+            if let Some(subscribers) = self.plan_subscribers.get(&(provider_address, plan_index)) {
+                for user in subscribers
+                    .iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                {
+                    if let Some(record) = self.active_record(*user, provider_address, plan_index) {
+                        data.push((*user, record.characteristics_values_encrypted.clone()));
+                    }
+                }
+            }
+            data
+        }
+
+        /// get statistics of `plan_index` of `provider_address`
+        ///
+        /// # Note
+        /// A subscription is counted as expired from the day after the day that it ends in,
+        /// so `active_subscribers` is accurate in days like the escrow
+        ///
+        /// # Returns
+        /// `PlanStats` struct is returned
+        ///
+        /// # Panics
+        /// If `caller` is not the provider or its operator
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in plan_stats_works test
+        #[ink(message)]
+        pub fn get_plan_stats(&self, provider_address: AccountId, plan_index: u128) -> PlanStats {
+            self.assert_provider_access(provider_address);
+            let counters: PlanCounters =
+                match self.plan_counters.get(&(provider_address, plan_index)) {
+                    Some(counters) => *counters,
+                    None => PlanCounters::default(),
+                };
+            let active_subscribers: u128 = self.active_subscribers_of(provider_address, plan_index);
+            PlanStats {
+                subscriptions: counters.subscriptions,
+                renewals: counters.renewals,
+                refunds: counters.refunds,
+                total_revenue: counters.total_revenue,
+                refunded_volume: counters.refunded_volume,
+                active_subscribers,
+                churn: checked_sub(counters.subscriptions, active_subscribers).expect(MATH_ERROR),
+            }
+        }
+
+        /// get daily statistics of `plan_index` of `provider_address` from `from_day` to `to_day`,
+        /// day ids are the same as the escrow day ids
+        ///
+        /// # Returns
+        /// `Vec<(u64, PlanDayStats)>` is returned which is a vector of day ids and their
+        /// statistics, days without any change are omitted
+        ///
+        /// # Panics
+        /// If `caller` is not the provider or its operator
+        /// If `to_day` is less than `from_day` or the range is more than `MAX_PAGE_LIMIT` days
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in plan_stats_works test
+        #[ink(message)]
+        pub fn get_plan_stats_history(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            from_day: u64,
+            to_day: u64,
+        ) -> Vec<(u64, PlanDayStats)> {
+            self.assert_provider_access(provider_address);
+            assert!(
+                from_day <= to_day && to_day - from_day < u64::from(MAX_PAGE_LIMIT),
+                "Page limit is too big"
+            );
+            let mut data: Vec<(u64, PlanDayStats)> = Vec::new();
+            for day_id in from_day..=to_day {
+                if let Some(day_stats) =
+                    self.plan_day_stats
+                        .get(&(provider_address, plan_index, day_id))
+                {
+                    data.push((day_id, *day_stats));
+                }
+            }
+            data
+        }
+
+        /// get a page of all-time subscribers of `provider_address`, each row is the last
+        /// subscription of a user to a plan of provider
+        ///
+        /// # Note
+        /// Subscribers are never removed from the list, refunded, cancelled and expired ones are
+        /// kept with their status and a user that subscribes again keeps its place.
+        /// At most `limit` subscriptions are visited from `cursor` in the order of their first
+        /// subscription and only the ones matching `plan_index` and `status` filters are
+        /// returned, so a page may have less than `limit` rows
+        ///
+        /// # Returns
+        /// `(Vec<SubscriberRow>, Option<u32>)` is returned which is the rows and the cursor of
+        /// the next page, the cursor is `None` if there is no more subscriptions
+        ///
+        /// # Panics
+        /// If `caller` is not the provider or its operator
+        /// If `limit` is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in get_subscribers_works test
+        #[ink(message)]
+        pub fn get_subscribers(
+            &self,
+            provider_address: AccountId,
+            plan_index: Option<u128>,
+            status: Option<SubscriptionStatus>,
+            cursor: u32,
+            limit: u32,
+        ) -> (Vec<SubscriberRow>, Option<u32>) {
+            self.assert_provider_access(provider_address);
+            assert!(limit <= MAX_PAGE_LIMIT, "Page limit is too big");
+            let subscriptions: Vec<(AccountId, u128)> = match plan_index {
+                Some(plan_index) => {
+                    match self.plan_subscribers.get(&(provider_address, plan_index)) {
+                        Some(subscribers) => subscribers
+                            .iter()
+                            .skip(cursor as usize)
+                            .take(limit as usize)
+                            .map(|user| (*user, plan_index))
+                            .collect(),
+                        None => Vec::new(),
+                    }
+                }
+                None => match self.provider_subscriptions.get(&provider_address) {
+                    Some(subscriptions) => subscriptions
+                        .iter()
+                        .skip(cursor as usize)
+                        .take(limit as usize)
+                        .copied()
+                        .collect(),
+                    None => Vec::new(),
+                },
+            };
+
+            let mut rows: Vec<SubscriberRow> = Vec::new();
+            for (user, plan_index) in subscriptions.iter() {
+                let record: &SubscriptionRecord =
+                    self.last_record(*user, provider_address, *plan_index);
+                let row_status: SubscriptionStatus = self
+                    .subscription_info_of(*user, provider_address, *plan_index)
+                    .unwrap()
+                    .status;
+                if status.is_none() || status == Some(row_status) {
+                    rows.push(SubscriberRow {
+                        user: *user,
+                        plan_index: *plan_index,
+                        start: record.subscription_time,
+                        end: checked_add_time(record.subscription_time, record.plan.duration)
+                            .expect(MATH_ERROR),
+                        status: row_status,
+                    });
+                }
+            }
+            let next_cursor: Option<u32> = if subscriptions.len() == limit as usize && limit > 0 {
+                Some(cursor.checked_add(limit).expect(MATH_ERROR))
+            } else {
+                None
+            };
+            (rows, next_cursor)
+        }
+
+        /// We can get the public profile of `provider_address` in this function
+        ///
+        /// # Returns
+        /// `ProviderProfile` is returned, it's empty if the provider has not set it
+        ///
+        /// # Panics
+        /// If `provider_address` is not a registered provider
+        ///
+        /// # Example
+        /// Examples in `provider_profile_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_provider_profile(&self, provider_address: AccountId) -> ProviderProfile {
+            assert!(
+                self.providers.contains_key(&provider_address),
+                "provider address is not valid!"
+            );
+            match self.provider_profiles.get(&provider_address) {
+                Some(profile) => profile.clone(),
+                None => ProviderProfile::default(),
+            }
+        }
+
+        /// get a page of registered providers in the order of their registration
+        ///
+        /// # Returns
+        /// `(Vec<AccountId>, Option<u32>)` is returned which is the providers and the cursor of
+        /// the next page, the cursor is `None` if there is no more providers
+        ///
+        /// # Panics
+        /// If `limit` is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in get_catalog_works test
+        #[ink(message)]
+        pub fn get_providers(&self, cursor: u32, limit: u32) -> (Vec<AccountId>, Option<u32>) {
+            assert!(limit <= MAX_PAGE_LIMIT, "Page limit is too big");
+            let end: u32 = core::cmp::min(cursor.saturating_add(limit), self.provider_list.len());
+            let providers: Vec<AccountId> = (cursor..end)
+                .map(|index| *self.provider_list.get(index).unwrap())
+                .collect();
+            let next_cursor: Option<u32> = if end < self.provider_list.len() {
+                Some(end)
+            } else {
+                None
+            };
+            (providers, next_cursor)
+        }
+
+        /// get data of the bundle with `bundle_id`
+        ///
+        /// # Panics
+        /// If the bundle does not exist
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in bundle_works test
+        #[ink(message)]
+        pub fn get_bundle(&self, bundle_id: u32) -> Bundle {
+            match self.bundles.get(bundle_id) {
+                Some(bundle) => bundle.clone(),
+                None => panic!("Bundle doesn't exist"),
+            }
+        }
+
+        /// get a page of bundles in the order of their creation, index of each bundle in the order
+        /// is its id
+        ///
+        /// # Returns
+        /// `(Vec<Bundle>, Option<u32>)` is returned which is the bundles and the cursor of
+        /// the next page, the cursor is `None` if there is no more bundles
+        ///
+        /// # Panics
+        /// If `limit` is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in bundle_works test
+        #[ink(message)]
+        pub fn get_bundles(&self, cursor: u32, limit: u32) -> (Vec<Bundle>, Option<u32>) {
+            assert!(limit <= MAX_PAGE_LIMIT, "Page limit is too big");
+            let end: u32 = core::cmp::min(cursor.saturating_add(limit), self.bundles.len());
+            let bundles: Vec<Bundle> = (cursor..end)
+                .map(|index| self.bundles.get(index).unwrap().clone())
+                .collect();
+            let next_cursor: Option<u32> = if end < self.bundles.len() {
+                Some(end)
+            } else {
+                None
+            };
+            (bundles, next_cursor)
+        }
+
+        /// We can get every plan of `provider_address` with its characteristics keys in this
+        /// function
+        ///
+        /// # Returns
+        /// `Vec<CatalogEntry>` is returned
+        ///
+        /// # Panics
+        /// If `provider_address` is not a registered provider
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in get_catalog_works test
+        #[ink(message)]
+        pub fn get_catalog(&self, provider_address: AccountId) -> Vec<CatalogEntry> {
+            let provider: &Provider = match self.providers.get(&provider_address) {
+                Some(provider) => provider,
+                None => panic!("provider address is not valid!"),
+            };
+            provider
+                .plans_characteristics
+                .iter()
+                .enumerate()
+                .map(|(plan_index, characteristics)| {
+                    let consts: PlanConsts =
+                        self.plan_consts_of(provider_address, plan_index as u128);
+                    CatalogEntry {
+                        plan_index: plan_index as u128,
+                        consts,
+                        characteristics: characteristics.clone(),
+                        enabled: !consts.disabled,
+                        sale_window: self.get_sale_window(provider_address, plan_index as u128),
+                    }
+                })
+                .collect()
+        }
+
+        /// get a page of the version history of `plan_index` of `provider_address`, each edit
+        /// of the plan creates a new version and the first version is the one that is added
+        ///
+        /// # Returns
+        /// `(Vec<PlanVersion>, Option<u32>)` is returned which is the versions from version
+        /// `cursor` and the cursor of the next page, the cursor is `None` if there is no more
+        /// versions
+        ///
+        /// # Panics
+        /// If `limit` is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in plan_versions_works test
+        #[ink(message)]
+        pub fn get_plan_versions(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            cursor: u32,
+            limit: u32,
+        ) -> (Vec<PlanVersion>, Option<u32>) {
+            assert!(limit <= MAX_PAGE_LIMIT, "Page limit is too big");
+            let versions: &Vec<PlanVersion> =
+                match self.plan_versions.get(&(provider_address, plan_index)) {
+                    Some(versions) => versions,
+                    None => return (Vec::new(), None),
+                };
+            let page: Vec<PlanVersion> = versions
+                .iter()
+                .skip(cursor as usize)
+                .take(limit as usize)
+                .copied()
+                .collect();
+            let next_cursor: Option<u32> =
+                if (cursor as usize).saturating_add(limit as usize) < versions.len() {
+                    Some(cursor.checked_add(limit).expect(MATH_ERROR))
+                } else {
+                    None
+                };
+            (page, next_cursor)
+        }
+
+        /// We can get `version` of `plan_index` of `provider_address` in this function,
+        /// `plan_version` of a `SubscriptionRecord` is the version that the user has bought
+        ///
+        /// # Returns
+        /// `PlanVersion` is returned
+        ///
+        /// # Panics
+        /// If the version doesn't exist
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs` in plan_versions_works test
+        #[ink(message)]
+        pub fn get_plan_version(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            version: u32,
+        ) -> PlanVersion {
+            match self
+                .plan_versions
+                .get(&(provider_address, plan_index))
+                .and_then(|versions| versions.get(version as usize))
+            {
+                Some(plan_version) => *plan_version,
+                None => panic!("Plan version doesn't exist"),
+            }
+        }
+
+        /// We can get the pending change of `plan_index` of `provider_address` in this function
+        ///
+        /// # Returns
+        /// `Option<PlanChange>` is returned which is `None` if there is no change that is not
+        /// effective yet
+        ///
+        /// # Example
+        /// Examples in `schedule_plan_change_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_pending_plan_change(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> Option<PlanChange> {
+            match self.plan_changes.get(&(provider_address, plan_index)) {
+                Some(change) if change.effective_at > self.env().block_timestamp() => Some(*change),
+                _ => None,
+            }
+        }
+
+        /// We can get the minimum time between scheduling a plan change and applying it in this
+        /// function
+        #[ink(message)]
+        pub fn get_notice_period(&self) -> u64 {
+            self.notice_period
+        }
+
+        /// We can get the capacity configs of `plan_index` of `provider_address` in this function
+        ///
+        /// # Returns
+        /// `Option<PlanCapacity>` is returned which is `None` if the plan has no capacity limit
+        ///
+        /// # Example
+        /// Examples in `waitlist_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_plan_capacity(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> Option<PlanCapacity> {
+            self.plan_capacities
+                .get(&(provider_address, plan_index))
+                .copied()
+        }
+
+        /// We can get the waitlist of `plan_index` of `provider_address` in this function
+        ///
+        /// # Note
+        /// Freed seats are offered when a function that changes the plan subscribers or
+        /// `process_waitlist` is called, so the result may be behind the expired subscriptions
+        ///
+        /// # Returns
+        /// `(Vec<WaitlistEntry>, Vec<SeatOffer>)` is returned which is the waiting users in order
+        /// and the users that are offered a seat
+        ///
+        /// # Example
+        /// Examples in `waitlist_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_waitlist(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> (Vec<WaitlistEntry>, Vec<SeatOffer>) {
+            (
+                self.waitlists
+                    .get(&(provider_address, plan_index))
+                    .cloned()
+                    .unwrap_or_default(),
+                self.seat_offers
+                    .get(&(provider_address, plan_index))
+                    .cloned()
+                    .unwrap_or_default(),
+            )
+        }
+
+        /// We can get the sale window of `plan_index` of `provider_address` in this function
+        ///
+        /// # Returns
+        /// `Option<SaleWindow>` is returned which is `None` if the plan has no sale window
+        ///
+        /// # Example
+        /// Examples in `sale_window_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_sale_window(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> Option<SaleWindow> {
+            self.sale_windows
+                .get(&(provider_address, plan_index))
+                .copied()
+        }
+
+        /// This function can be called to check if users can subscribe to `plan_index` of
+        /// `provider_address` now regarding its sale window
+        ///
+        /// # Returns
+        /// `bool` which means if the plan is on sale or not
+        ///
+        /// # Example
+        /// Examples in `sale_window_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn is_on_sale(&self, provider_address: AccountId, plan_index: u128) -> bool {
+            self.is_in_sale_window(provider_address, plan_index, false)
+        }
+
+        /// We can get plan count in this function
+        ///
+        /// # Returns
+        /// a number is returned
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_plan_length(&self, provider_address: AccountId) -> u128 {
+            match self.providers.get(&provider_address) {
+                Some(provider) => provider.plans.len().try_into().unwrap(),
+                None => 0,
+            }
+        }
+
+        /// We can get plan characteristic keys in this function
+        ///
+        /// # Returns
+        /// `Vec<String>` is returned
+        ///
+        /// # Example
+        /// Examples in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_plan_characteristics(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> Vec<String> {
+            let number: usize = plan_index.try_into().unwrap();
+            match match self.providers.get(&provider_address) {
+                Some(provider) => provider,
+                None => panic!("index is not valid!"),
+            }
+            .plans_characteristics
+            .get(number)
+            {
+                Some(x) => x.clone(),
+                None => panic!("please select a valid plan"),
+            }
+        }
+
+        /// We can get plan characteristics with their rules in this function
+        ///
+        /// # Returns
+        /// `Vec<Characteristic>` is returned
+        ///
+        /// # Example
+        /// Examples in `characteristic_schema_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_characteristic_schema(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> Vec<Characteristic> {
+            self.get_plan_characteristics(provider_address, plan_index)
+                .into_iter()
+                .enumerate()
+                .map(|(index, key)| Characteristic {
+                    key,
+                    rules: self.characteristic_rules_of(provider_address, plan_index, index),
+                })
+                .collect()
+        }
+
+        /// We can get the payout table which is used for payments of `plan_index` of the provider
+        /// in this function
+        ///
+        /// # Returns
+        /// `Vec<PayoutShare>` is returned, if it's empty the whole money goes to `money_address`
+        ///
+        /// # Example
+        /// Examples in `payout_table_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_payout_table(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> Vec<PayoutShare> {
+            let number: usize = plan_index.try_into().unwrap();
+            match self.providers.get(&provider_address) {
+                Some(provider) => {
+                    assert!(provider.plans.len() > number, "please select a valid plan");
+                    Self::payout_table_of(provider, number).to_vec()
+                }
+                None => panic!("provider address is not valid!"),
+            }
+        }
+
+        /// Get hash of String
+        ///
+        /// # Note
+        /// This is the unsalted hash that clients send as `pass`, so pass_phrases must be hashed
+        /// off-chain and must not be sent to this function
+        ///
+        /// # Returns
+        /// `String` is returned
+        ///    
+        #[ink(message)]
+        pub fn get_sha2(&self, string: String) -> [u8; 32] {
+            let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
+            ink_env::hash_encoded::<Sha2x256, _>(&string, &mut output);
+            return output;
+        }
+
+        /// Get the message hash that `account` has to sign for answering the login challenge
+        /// `nonce` which is issued at `issued_at`
+        ///
+        /// # Returns
+        /// `Blake2x256` hash of SCALE encoded (`LOGIN_MESSAGE_PREFIX`, `account`, `nonce`, `issued_at`)
+        #[ink(message)]
+        pub fn get_login_message_hash(
+            &self,
+            account: AccountId,
+            nonce: [u8; 32],
+            issued_at: u64,
+        ) -> [u8; 32] {
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(
+                &(LOGIN_MESSAGE_PREFIX, account, nonce, issued_at),
+                &mut output,
+            );
+            output
+        }
+
+        /// Get the current public encryption key of `provider_address`
+        ///
+        /// # Returns
+        /// `(u32, Vec<u8>)` which is the version of the key and the key
+        ///
+        /// # Panics
+        /// If provider is not valid or has not published any key
+        ///
+        /// # Example
+        /// Examples in `encryption_key_rotation_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_encryption_key(&self, provider_address: AccountId) -> (u32, Vec<u8>) {
+            let version: u32 = self.current_key_version(provider_address);
+            (
+                version,
+                self.get_encryption_key_by_version(provider_address, version),
+            )
+        }
+
+        /// Get the public encryption key of `provider_address` with `version`
+        ///
+        /// # Panics
+        /// If provider is not valid or the key doesn't exist
+        #[ink(message)]
+        pub fn get_encryption_key_by_version(
+            &self,
+            provider_address: AccountId,
+            version: u32,
+        ) -> Vec<u8> {
+            let provider: &Provider = match self.providers.get(&provider_address) {
+                Some(provider) => provider,
+                None => panic!("provider address is not valid!"),
+            };
+            let index: usize = version.try_into().unwrap();
+            match index
+                .checked_sub(1)
+                .and_then(|i| provider.encryption_keys.get(i))
+            {
+                Some(key) => key.clone(),
+                None => panic!("Encryption key doesn't exist"),
+            }
+        }
+
+        /// This function can be called to check if `user` has a valid subscription to the
+        /// specific `plan_index` of `provider`.
+        ///
+        /// # Note
+        /// if `user` refunded or her subscription is expired then this function will return false
+        ///
+        /// # Returns
+        /// `bool` which means if `user` is subscribed or not
+        ///
+        /// # Example
+        /// Examples in `check_subscription_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn check_subscription(
+            &self,
+            user: AccountId,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> bool {
+            if !self
+                .plan_index_to_record_index
+                .contains_key(&(user, provider_address, plan_index))
+            {
+                return false;
+            }
+            let last_index: u128 = *self
+                .plan_index_to_record_index
+                .get(&(user, provider_address, plan_index))
+                .unwrap();
+            let number: usize = last_index.try_into().unwrap();
+            let record: &SubscriptionRecord = &self
+                .records
+                .get(&(user, provider_address))
+                .unwrap()
+                .subscription_records[number];
+            if record.plan_index != plan_index
+                || record.refunded
+                || checked_add_time(record.plan.duration, record.subscription_time)
+                    .expect(MATH_ERROR)
+                    < self.env().block_timestamp()
+            {
+                return false;
+            }
+            true
+        }
+
+        /// This function can be called to check if `user` has a valid subscription to the
+        /// specific `plan_index` of `provider`.
+        ///
+        /// # Note
+        /// if `user` refunded or her subscription is expired then this function will return false
+        ///
+        /// # Returns
+        /// `bool` which means if `user` is subscribed or not
+        ///
+        /// # Example
+        /// Examples in `check_subscription_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn check_subscription_with_username(
+            &self,
+            username: String,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> bool {
+            match self.username_to_address.get(&username) {
+                Some(name) => self.check_subscription(*name, provider_address, plan_index),
+                None => false,
+            }
+        }
+
+        /// This function can be called to check many subscriptions to the plans of `provider` in
+        /// one call. Each query is a user and a `plan_index`.
+        ///
+        /// # Note
+        /// Unknown usernames are not subscribed to any plan
+        ///
+        /// # Returns
+        /// `Vec<bool>` is returned which is the result of `check_subscription` for each query
+        ///
+        /// # Panics
+        /// If the number of queries is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `check_subscriptions_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn check_subscriptions(
+            &self,
+            provider_address: AccountId,
+            queries: Vec<(UserIdentifier, u128)>,
+        ) -> Vec<bool> {
+            assert!(
+                queries.len() <= MAX_PAGE_LIMIT as usize,
+                "Page limit is too big"
+            );
+            queries
+                .iter()
+                .map(|(user, plan_index)| match self.resolve_user(user) {
+                    Some(user) => self.check_subscription(user, provider_address, *plan_index),
+                    None => false,
+                })
+                .collect()
+        }
+
+        /// This function can be called to check if each of `users` has a valid subscription to
+        /// any plan of `provider`.
+        ///
+        /// # Returns
+        /// `Vec<bool>` is returned which is the result of checking all plans for each user
+        ///
+        /// # Panics
+        /// If the number of users is more than `MAX_PAGE_LIMIT`
+        ///
+        /// # Example
+        /// Examples in `check_subscriptions_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn check_any_subscriptions(
+            &self,
+            provider_address: AccountId,
+            users: Vec<UserIdentifier>,
+        ) -> Vec<bool> {
+            assert!(
+                users.len() <= MAX_PAGE_LIMIT as usize,
+                "Page limit is too big"
+            );
+            let plan_length: u128 = self.get_plan_length(provider_address);
+            users
+                .iter()
+                .map(|user| match self.resolve_user(user) {
+                    Some(user) => (0..plan_length).any(|plan_index| {
+                        self.check_subscription(user, provider_address, plan_index)
+                    }),
+                    None => false,
+                })
+                .collect()
+        }
+
+        /// This function can be called to get the status of the subscription of `user` to the
+        /// specific `plan_index` of `provider`.
+        ///
+        /// # Note
+        /// `Paused` means that the subscription is valid but the plan is disabled by provider, so
+        /// it can't be renewed until the provider enables it again
+        ///
+        /// # Returns
+        /// `SubscriptionInfo` struct is returned
+        ///
+        /// # Panics
+        /// If `user` has never subscribed to `plan_index` of `provider_address`
+        ///
+        /// # Example
+        /// Examples in `get_subscription_status_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn get_subscription_status(
+            &self,
+            user: AccountId,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> SubscriptionInfo {
+            match self.subscription_info_of(user, provider_address, plan_index) {
+                Some(info) => info,
+                None => panic!("User has never subscribed to this plan"),
+            }
+        }
+
+        /// Registering an access token of the `caller` for `plan_index` of `provider_address`
+        /// which is valid until `expires_at`
+        ///
+        /// # Note
+        /// Only `token_hash` which is `Sha2x256` hash of the token is stored in the contract. The
+        /// expired tokens of the `caller` are removed in this function.
+        ///
+        /// # Panics
+        /// If `caller` is not subscribed to `plan_index` of `provider_address`
+        /// If `expires_at` is passed or is more than `MAX_ACCESS_TOKEN_LIFETIME` later
+        /// If `token_hash` is already registered
+        /// If `caller` has `MAX_ACCESS_TOKENS` unexpired tokens
+        ///
+        /// # Example
+        /// Examples in `access_token_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn register_access_token(
+            &mut self,
+            token_hash: [u8; 32],
+            provider_address: AccountId,
+            plan_index: u128,
+            expires_at: u64,
+        ) {
+            let caller: AccountId = self.env().caller();
+            let now: u64 = self.env().block_timestamp();
+            assert!(
+                self.check_subscription(caller, provider_address, plan_index),
+                "You are not subscribed to this plan"
+            );
+            assert!(
+                expires_at > now && expires_at - now <= MAX_ACCESS_TOKEN_LIFETIME,
+                "Invalid expiry time"
+            );
+            assert!(
+                !self.access_tokens.contains_key(&token_hash),
+                "Access token already exists"
+            );
+
+            if !self.user_access_tokens.contains_key(&caller) {
+                self.user_access_tokens.insert(caller, Vec::new());
+            }
+            let mut token_hashes: Vec<[u8; 32]> = Vec::new();
+            for hash in self.user_access_tokens.get(&caller).unwrap().iter() {
+                if self.access_tokens.get(hash).unwrap().expires_at > now {
+                    token_hashes.push(*hash);
+                } else {
+                    self.access_tokens.take(hash);
+                }
+            }
+            assert!(
+                token_hashes.len() < MAX_ACCESS_TOKENS,
+                "Too many access tokens"
+            );
+            token_hashes.push(token_hash);
+            self.user_access_tokens.insert(caller, token_hashes);
+            self.access_tokens.insert(
+                token_hash,
+                AccessToken {
+                    user: caller,
+                    provider: provider_address,
+                    plan_index,
+                    expires_at,
+                },
+            );
+        }
+
+        /// Revoking the access token of the `caller` with `token_hash`
+        ///
+        /// # Panics
+        /// If `token_hash` is not a token of the `caller`
+        ///
+        /// # Example
+        /// Examples in `access_token_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn revoke_access_token(&mut self, token_hash: [u8; 32]) {
+            let token_hashes: &mut Vec<[u8; 32]> =
+                match self.user_access_tokens.get_mut(&self.env().caller()) {
+                    Some(token_hashes) => token_hashes,
+                    None => panic!("Access token doesn't exist"),
+                };
+            match token_hashes.iter().position(|hash| *hash == token_hash) {
+                Some(index) => {
+                    token_hashes.swap_remove(index);
+                }
+                None => panic!("Access token doesn't exist"),
+            }
+            self.access_tokens.take(&token_hash);
+        }
+
+        /// Listing the access tokens of the `caller`
+        ///
+        /// # Returns
+        /// `Vec<([u8; 32], AccessToken)>` is returned which is a vector of token hashes and
+        /// their `AccessToken` struct
+        ///
+        /// # Example
+        /// Examples in `access_token_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn list_access_tokens(&self) -> Vec<([u8; 32], AccessToken)> {
+            let mut data: Vec<([u8; 32], AccessToken)> = Vec::new();
+            if let Some(token_hashes) = self.user_access_tokens.get(&self.env().caller()) {
+                for hash in token_hashes.iter() {
+                    data.push((*hash, *self.access_tokens.get(hash).unwrap()));
+                }
+            }
+            data
+        }
+
+        /// This function can be called by a provider or its operators to check if `token` belongs
+        /// to an active subscriber of the provider
+        ///
+        /// # Returns
+        /// `bool` which is true if `token` is registered for the provider, is not expired and its
+        /// user is still subscribed to the plan of the token
+        ///
+        /// # Example
+        /// Examples in `access_token_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn verify_access_token(&self, token: String) -> bool {
+            let mut token_hash = <Sha2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Sha2x256, _>(&token, &mut token_hash);
+            match self.access_tokens.get(&token_hash) {
+                Some(access_token) => {
+                    self.is_provider_or_operator(access_token.provider, self.env().caller())
+                        && access_token.expires_at > self.env().block_timestamp()
+                        && self.check_subscription(
+                            access_token.user,
+                            access_token.provider,
+                            access_token.plan_index,
+                        )
+                }
+                None => false,
+            }
+        }
+
+        /// Granting `grantee` read access to records of the `caller` in `provider` until
+        /// `expires_at`, if `provider` is `None` the records of all providers can be read
+        ///
+        /// # Note
+        /// If `grantee` already has a grant for `provider`, its expiry is updated. The expired
+        /// grants of the `caller` are removed in this function.
+        ///
+        /// # Panics
+        /// If `caller` does not exist in `users`
+        /// If `expires_at` is passed
+        /// If `caller` has `MAX_READ_GRANTS` unexpired grants
+        ///
+        /// # Example
+        /// Examples in `read_grant_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn grant_read_access(
+            &mut self,
+            grantee: AccountId,
+            provider: Option<AccountId>,
+            expires_at: u64,
+        ) {
+            let caller: AccountId = self.env().caller();
+            let now: u64 = self.env().block_timestamp();
+            assert!(self.users.contains_key(&caller), "User doesn't exist!");
+            assert!(expires_at > now, "Invalid expiry time");
+
+            let mut grants: Vec<ReadGrant> = match self.read_grants.take(&caller) {
+                Some(grants) => grants
+                    .into_iter()
+                    .filter(|grant| grant.expires_at > now)
+                    .collect(),
+                None => Vec::new(),
+            };
+            match grants
+                .iter_mut()
+                .find(|grant| grant.grantee == grantee && grant.provider == provider)
+            {
+                Some(grant) => grant.expires_at = expires_at,
+                None => {
+                    assert!(grants.len() < MAX_READ_GRANTS, "Too many read grants");
+                    grants.push(ReadGrant {
+                        grantee,
+                        provider,
+                        expires_at,
+                    });
+                }
+            }
+            self.read_grants.insert(caller, grants);
+        }
+
+        /// Revoking the read access of `grantee` to records of the `caller` in `provider`
+        ///
+        /// # Panics
+        /// If there is no grant for `grantee` and `provider`
+        ///
+        /// # Example
+        /// Examples in `read_grant_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn revoke_read_access(&mut self, grantee: AccountId, provider: Option<AccountId>) {
+            let grants: &mut Vec<ReadGrant> = match self.read_grants.get_mut(&self.env().caller()) {
+                Some(grants) => grants,
+                None => panic!("Read grant doesn't exist"),
+            };
+            match grants
+                .iter()
+                .position(|grant| grant.grantee == grantee && grant.provider == provider)
+            {
+                Some(index) => {
+                    grants.swap_remove(index);
+                }
+                None => panic!("Read grant doesn't exist"),
+            }
+        }
+
+        /// Listing the read grants of the `caller`
+        ///
+        /// # Returns
+        /// `Vec<ReadGrant>` is returned which is a vector of `ReadGrant` struct
+        #[ink(message)]
+        pub fn get_read_grants(&self) -> Vec<ReadGrant> {
+            match self.read_grants.get(&self.env().caller()) {
+                Some(grants) => grants.clone(),
+                None => Vec::new(),
+            }
+        }
+
+        /// The `caller` can use this function to retrieve subscriptions history of `user` for a
+        /// specific provider if `user` granted read access to the `caller`
+        ///
+        /// # Returns
+        /// `Vec<SubscriptionRecord>` is returned which is a vector of `SubscriptionRecord` struct
+        ///
+        /// # Panics
+        /// If the `caller` is not `user` and has no unexpired grant for `provider_address`
+        ///
+        /// # Example
+        /// Examples in `read_grant_works` in `tests/test.rs`
+        #[ink(message)]
+        pub fn retrieve_data_for(
+            &self,
+            user: AccountId,
+            provider_address: AccountId,
+        ) -> Vec<SubscriptionRecord> {
+            assert!(
+                self.has_read_access(user, provider_address, self.env().caller()),
+                "You don't have read access"
+            );
+            self.retrieve_data(user, provider_address)
+        }
+
+        fn retrieve_whole_data(&self, caller: AccountId) -> Vec<SubscriptionRecord> {
+            assert!(self.users.contains_key(&caller));
+            let mut data: Vec<SubscriptionRecord> = Vec::new();
+            let user: &User = self.users.get(&caller).unwrap();
+            for i in 0..user.list_of_providers.len() {
+                data.append(&mut self.retrieve_data(caller, user.list_of_providers[i]));
+            }
+            data
+        }
+
+        fn retrieve_data(
+            &self,
+            caller: AccountId,
+            provider_address: AccountId,
+        ) -> Vec<SubscriptionRecord> {
+            assert!(self.users.contains_key(&caller));
+            assert!(self.records.contains_key(&(caller, provider_address)));
+
+            let plan_records: &PlanRecord = self.records.get(&(caller, provider_address)).unwrap();
+            plan_records.subscription_records.clone()
+        }
+
+        /// filter_records : visits at most `limit` records of `user` from `cursor` and returns the
+        /// ones that match `filter` with the cursor of the next page
+        fn filter_records(
+            &self,
+            user: AccountId,
+            filter: RecordFilter,
+            cursor: RecordCursor,
+            limit: u32,
+        ) -> (Vec<&SubscriptionRecord>, Option<RecordCursor>) {
+            assert!(limit <= MAX_PAGE_LIMIT, "Page limit is too big");
+            let mut data: Vec<&SubscriptionRecord> = Vec::new();
+            let providers: &Vec<AccountId> = match self.users.get(&user) {
+                Some(user) => &user.list_of_providers,
+                None => return (data, None),
+            };
+            let mut provider_position: usize = cursor.provider_position as usize;
+            let mut record_index: usize = cursor.record_index as usize;
+            let mut visited: u32 = 0;
+            while provider_position < providers.len() {
+                let provider_address: AccountId = providers[provider_position];
+                if filter.provider.is_some() && filter.provider != Some(provider_address) {
+                    provider_position += 1;
+                    record_index = 0;
+                    continue;
+                }
+                let records: &Vec<SubscriptionRecord> = &self
+                    .records
+                    .get(&(user, provider_address))
+                    .unwrap()
+                    .subscription_records;
+                while record_index < records.len() {
+                    if visited == limit {
+                        return (
+                            data,
+                            Some(RecordCursor {
+                                provider_position: provider_position as u32,
+                                record_index: record_index as u32,
+                            }),
+                        );
+                    }
+                    visited += 1;
+                    let record: &SubscriptionRecord = &records[record_index];
+                    record_index += 1;
+                    if filter.plan_index.is_some_and(|plan_index| plan_index != record.plan_index) {
+                        continue;
+                    }
+                    if filter
+                        .status
+                        .as_ref()
+                        .is_some_and(|status| *status != self.subscription_status_of(record))
+                    {
+                        continue;
+                    }
+                    let end: u64 =
+                        checked_add_time(record.subscription_time, record.plan.duration)
+                            .expect(MATH_ERROR);
+                    if filter.from_time.is_some_and(|from_time| end < from_time)
+                        || filter
+                            .to_time
+                            .is_some_and(|to_time| record.subscription_time > to_time)
+                    {
+                        continue;
+                    }
+                    data.push(record);
+                }
+                provider_position += 1;
+                record_index = 0;
+            }
+            (data, None)
+        }
+
+        /// random_salt : returns a random salt for a new credential of `account` for `provider`,
+        /// `provider` keeps the salts of the credentials that are set in the same block apart
+        fn random_salt(&self, account: AccountId, provider: Option<AccountId>) -> Vec<u8> {
+            let (seed, _) = self
+                .env()
+                .random(&scale::Encode::encode(&(account, provider)));
+            seed.as_ref().to_vec()
+        }
+
+        /// build_credential : checks the parameters of a salted credential and builds it
+        fn build_credential(
+            salt: Vec<u8>,
+            algorithm: HashAlgorithm,
+            iterations: u32,
+            hash: [u8; 32],
+        ) -> Credential {
+            assert!(
+                !salt.is_empty() && salt.len() <= MAX_SALT_LENGTH,
+                "Invalid salt length"
+            );
+            assert!(
+                iterations > 0 && iterations <= MAX_CREDENTIAL_ITERATIONS,
+                "Invalid number of iterations"
+            );
+            Credential {
+                salt,
+                algorithm,
+                iterations,
+                hash,
+            }
+        }
+
+        /// add_plan_subscriber : appends `user` to the all-time subscribers of `plan_index` of
+        /// `provider_address`
+        fn add_plan_subscriber(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            user: AccountId,
+        ) {
+            if !self
+                .plan_subscribers
+                .contains_key(&(provider_address, plan_index))
+            {
+                self.plan_subscribers
+                    .insert((provider_address, plan_index), Vec::new());
+            }
+            self.plan_subscribers
+                .get_mut(&(provider_address, plan_index))
+                .unwrap()
+                .push(user);
+            if !self.provider_subscriptions.contains_key(&provider_address) {
+                self.provider_subscriptions
+                    .insert(provider_address, Vec::new());
+            }
+            self.provider_subscriptions
+                .get_mut(&provider_address)
+                .unwrap()
+                .push((user, plan_index));
+        }
+
+        /// assert_account_is_free : checks that `account` is not a user or a provider and has no
+        /// username
+        fn assert_account_is_free(&self, account: AccountId) {
+            assert!(
+                !self.users.contains_key(&account)
+                    && !self.providers.contains_key(&account)
+                    && !self.address_to_username.contains_key(&account),
+                "New account is already in use"
+            );
+        }
+
+        /// recovery_votes_of : checks that `guardian` is a guardian of `lost_account` and returns
+        /// the threshold, the majority of guardians and the delay of its recovery
+        fn recovery_votes_of(
+            &self,
+            lost_account: AccountId,
+            guardian: AccountId,
+        ) -> (usize, usize, u64) {
+            let config: &RecoveryConfig = match self.recovery_configs.get(&lost_account) {
+                Some(config) => config,
+                None => panic!("You are not a guardian of this account"),
+            };
+            assert!(
+                config.guardians.contains(&guardian),
+                "You are not a guardian of this account"
+            );
+            (
+                config.threshold as usize,
+                config.guardians.len() / 2 + 1,
+                config.delay,
+            )
+        }
+
+        /// recovery_approvals_of : returns the number of guardians that approved `new_account`
+        fn recovery_approvals_of(request: &RecoveryRequest, new_account: AccountId) -> usize {
+            request
+                .approvals
+                .iter()
+                .filter(|(_, account)| *account == new_account)
+                .count()
+        }
+
+        /// drop_unsupported_recovery : unsets the chosen account of `request` if less than
+        /// `threshold` guardians still approve it
+        fn drop_unsupported_recovery(request: &mut RecoveryRequest, threshold: usize) {
+            if let Some(new_account) = request.new_account {
+                if Self::recovery_approvals_of(request, new_account) < threshold {
+                    request.new_account = None;
+                    request.ready_at = None;
+                }
+            }
+        }
+
+        /// migrate_user : moves the user identity of `old_account` to `new_account`
+        fn migrate_user(&mut self, old_account: AccountId, new_account: AccountId) {
+            if let Some(username) = self.address_to_username.take(&old_account) {
+                self.username_to_address
+                    .insert(username.clone(), new_account);
+                self.address_to_username.insert(new_account, username);
+            }
+            let user: User = self.users.take(&old_account).unwrap();
+            for provider_address in user.list_of_providers.iter() {
+                let plan_record: PlanRecord =
+                    match self.records.take(&(old_account, *provider_address)) {
+                        Some(plan_record) => plan_record,
+                        None => continue,
+                    };
+                for record in plan_record.subscription_records.iter() {
+                    let plan_index: u128 = record.plan_index;
+                    let old_key = (old_account, *provider_address, plan_index);
+                    if let Some(last_index) = self.plan_index_to_record_index.take(&old_key) {
+                        self.plan_index_to_record_index
+                            .insert((new_account, *provider_address, plan_index), last_index);
+                        if let Some(subscribers) = self
+                            .plan_subscribers
+                            .get_mut(&(*provider_address, plan_index))
+                        {
+                            for subscriber in subscribers.iter_mut() {
+                                if *subscriber == old_account {
+                                    *subscriber = new_account;
+                                }
+                            }
+                        }
+                    }
+                }
+                if let Some(subscriptions) = self.provider_subscriptions.get_mut(provider_address) {
+                    for subscription in subscriptions.iter_mut() {
+                        if subscription.0 == old_account {
+                            subscription.0 = new_account;
+                        }
+                    }
+                }
+                self.records
+                    .insert((new_account, *provider_address), plan_record);
+            }
+            self.users.insert(new_account, user);
+
+            if let Some(token_hashes) = self.user_access_tokens.take(&old_account) {
+                for hash in token_hashes.iter() {
+                    self.access_tokens.take(hash);
+                }
+            }
+            self.read_grants.take(&old_account);
+            self.login_keys.take(&old_account);
+            if let Some(config) = self.recovery_configs.take(&old_account) {
+                self.recovery_configs.insert(new_account, config);
+            }
+            self.migrate_waitlists(old_account, new_account);
+        }
+
+        /// migrate_waitlists : moves the waitlist entries and seat offers of `old_account` to
+        /// `new_account`, the deposits of plans that `new_account` is already waiting for are paid
+        /// back to `new_account`
+        fn migrate_waitlists(&mut self, old_account: AccountId, new_account: AccountId) {
+            let joined: Vec<(AccountId, u128)> = match self.user_waitlists.take(&old_account) {
+                Some(joined) => joined,
+                None => return,
+            };
+            let mut moved: Vec<(AccountId, u128)> =
+                self.user_waitlists.take(&new_account).unwrap_or_default();
+            for (provider_address, plan_index) in joined {
+                if !self.is_in_waitlist(provider_address, plan_index, old_account) {
+                    continue;
+                }
+                if self.is_in_waitlist(provider_address, plan_index, new_account) {
+                    let deposit: u128 =
+                        match self.remove_seat_offer(provider_address, plan_index, old_account) {
+                            Some(deposit) => deposit,
+                            None => {
+                                let waitlist: &mut Vec<WaitlistEntry> = self
+                                    .waitlists
+                                    .get_mut(&(provider_address, plan_index))
+                                    .unwrap();
+                                let position: usize = waitlist
+                                    .iter()
+                                    .position(|entry| entry.user == old_account)
+                                    .unwrap();
+                                waitlist.remove(position).deposit
+                            }
+                        };
+                    self.pay_back_deposit(new_account, deposit);
+                } else {
+                    if let Some(waitlist) = self.waitlists.get_mut(&(provider_address, plan_index))
+                    {
+                        for entry in waitlist.iter_mut() {
+                            if entry.user == old_account {
+                                entry.user = new_account;
+                            }
+                        }
+                    }
+                    if let Some(offers) = self.seat_offers.get_mut(&(provider_address, plan_index))
+                    {
+                        for offer in offers.iter_mut() {
+                            if offer.user == old_account {
+                                offer.user = new_account;
+                            }
+                        }
+                    }
+                }
+                if !moved.contains(&(provider_address, plan_index)) {
+                    moved.push((provider_address, plan_index));
+                }
+            }
+            if !moved.is_empty() {
+                self.user_waitlists.insert(new_account, moved);
+            }
+        }
+
+        /// plan_consts_of : returns the current `PlanConsts` of `plan_index` of `provider_address`
+        /// including the scheduled change that is effective but not applied yet
+        fn plan_consts_of(&self, provider_address: AccountId, plan_index: u128) -> PlanConsts {
+            if let Some(change) = self.plan_changes.get(&(provider_address, plan_index)) {
+                if change.effective_at <= self.env().block_timestamp() {
+                    return change.consts;
+                }
+            }
+            let index: usize = plan_index.try_into().unwrap();
+            self.providers.get(&provider_address).unwrap().plans[index]
+        }
+
+        /// apply_plan_change : applies the scheduled change of `plan_index` of `provider_address`
+        /// if it's effective
+        fn apply_plan_change(&mut self, provider_address: AccountId, plan_index: u128) {
+            let change: PlanChange = match self.plan_changes.get(&(provider_address, plan_index)) {
+                Some(change) if change.effective_at <= self.env().block_timestamp() => *change,
+                _ => return,
+            };
+            self.plan_changes.take(&(provider_address, plan_index));
+            let index: usize = plan_index.try_into().unwrap();
+            self.providers.get_mut(&provider_address).unwrap().plans[index] = change.consts;
+            self.add_plan_version(
+                provider_address,
+                plan_index,
+                change.consts,
+                change.effective_at,
+            );
+        }
+
+        /// add_plan_version : appends `consts` that is changed at `changed_at` to the version
+        /// history of `plan_index` of `provider_address`
+        fn add_plan_version(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            consts: PlanConsts,
+            changed_at: u64,
+        ) {
+            let plan_version: PlanVersion = PlanVersion { consts, changed_at };
+            match self.plan_versions.get_mut(&(provider_address, plan_index)) {
+                Some(versions) => versions.push(plan_version),
+                None => {
+                    self.plan_versions
+                        .insert((provider_address, plan_index), vec![plan_version]);
+                }
+            }
+        }
+
+        /// current_plan_version : returns the version id of the current `PlanConsts` of
+        /// `plan_index` of `provider_address`
+        fn current_plan_version(&self, provider_address: AccountId, plan_index: u128) -> u32 {
+            match self.plan_versions.get(&(provider_address, plan_index)) {
+                Some(versions) => (versions.len() - 1).try_into().unwrap(),
+                None => 0,
+            }
+        }
+
+        /// current_key_version : returns the version of the last encryption key of
+        /// `provider_address` and 0 if it has no key
+        fn current_key_version(&self, provider_address: AccountId) -> u32 {
+            match self.providers.get(&provider_address) {
+                Some(provider) => provider.encryption_keys.len().try_into().unwrap(),
+                None => 0,
+            }
+        }
+
+        /// active_subscribers_of : returns the number of subscriptions of `plan_index` of
+        /// `provider_address` that are not refunded or expired before today, so subscriptions
+        /// that expire today still hold their seats
+        fn active_subscribers_of(&self, provider_address: AccountId, plan_index: u128) -> u128 {
+            let counters: PlanCounters =
+                match self.plan_counters.get(&(provider_address, plan_index)) {
+                    Some(counters) => *counters,
+                    None => PlanCounters::default(),
+                };
+            let today: u64 = self.day_id_of(self.env().block_timestamp());
+            let expired: u128 = self.expired_subscriptions(provider_address, plan_index, today);
+            counters
+                .subscriptions
+                .saturating_sub(counters.refunds)
+                .saturating_sub(expired)
+        }
+
+        /// update_waitlist : pays the deposits of expired seat offers of `plan_index` of
+        /// `provider_address` to provider and offers the free seats to the head of the waitlist
+        fn update_waitlist(&mut self, provider_address: AccountId, plan_index: u128) {
+            let capacity: PlanCapacity =
+                match self.plan_capacities.get(&(provider_address, plan_index)) {
+                    Some(capacity) => *capacity,
+                    None => return,
+                };
+            let now: u64 = self.env().block_timestamp();
+            let mut offers: Vec<SeatOffer> = self
+                .seat_offers
+                .take(&(provider_address, plan_index))
+                .unwrap_or_default();
+            let mut forfeited: u128 = 0;
+            offers.retain(|offer| {
+                if offer.expires_at < now {
+                    forfeited = checked_add(forfeited, offer.deposit).expect(MATH_ERROR);
+                    false
+                } else {
+                    true
+                }
+            });
+            if forfeited > 0 {
+                self.total_deposits =
+                    checked_sub(self.total_deposits, forfeited).expect(MATH_ERROR);
+                assert_eq!(
+                    self.pay_provider(provider_address, plan_index, forfeited),
+                    Ok(())
+                );
+            }
+
+            let occupied: u128 = checked_add(
+                self.active_subscribers_of(provider_address, plan_index),
+                offers.len() as u128,
+            )
+            .expect(MATH_ERROR);
+            let free_seats: usize = capacity
+                .max_subscribers
+                .saturating_sub(occupied)
+                .try_into()
+                .unwrap_or(usize::MAX);
+            if let Some(waitlist) = self.waitlists.get_mut(&(provider_address, plan_index)) {
+                let count: usize = core::cmp::min(free_seats, waitlist.len());
+                for entry in waitlist.drain(..count) {
+                    offers.push(SeatOffer {
+                        user: entry.user,
+                        deposit: entry.deposit,
+                        expires_at: checked_add_time(now, capacity.claim_window).expect(MATH_ERROR),
+                    });
+                }
+            }
+            if !offers.is_empty() {
+                self.seat_offers
+                    .insert((provider_address, plan_index), offers);
+            }
+        }
+
+        /// take_seat : checks that `plan_index` of `provider_address` has a free seat for `user`
+        /// who is subscribing and uses the seat that is offered to `user` if there is any
+        fn take_seat(&mut self, provider_address: AccountId, plan_index: u128, user: AccountId) {
+            self.update_waitlist(provider_address, plan_index);
+            let capacity: PlanCapacity =
+                match self.plan_capacities.get(&(provider_address, plan_index)) {
+                    Some(capacity) => *capacity,
+                    None => return,
+                };
+            if let Some(deposit) = self.remove_seat_offer(provider_address, plan_index, user) {
+                self.pay_back_deposit(user, deposit);
+                return;
+            }
+            let offers: u128 = match self.seat_offers.get(&(provider_address, plan_index)) {
+                Some(offers) => offers.len() as u128,
+                None => 0,
+            };
+            assert!(
+                checked_add(
+                    self.active_subscribers_of(provider_address, plan_index),
+                    offers
+                )
+                .expect(MATH_ERROR)
+                    < capacity.max_subscribers,
+                "Plan is full"
+            );
+        }
+
+        /// remove_seat_offer : removes the seat offer of `user` and returns its deposit
+        fn remove_seat_offer(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            user: AccountId,
+        ) -> Option<u128> {
+            let offers: &mut Vec<SeatOffer> =
+                self.seat_offers.get_mut(&(provider_address, plan_index))?;
+            let position: usize = offers.iter().position(|offer| offer.user == user)?;
+            Some(offers.remove(position).deposit)
+        }
+
+        /// is_in_waitlist : checks if `user` is waiting for or is offered a seat of `plan_index`
+        /// of `provider_address`
+        fn is_in_waitlist(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            user: AccountId,
+        ) -> bool {
+            let waiting: bool = match self.waitlists.get(&(provider_address, plan_index)) {
+                Some(waitlist) => waitlist.iter().any(|entry| entry.user == user),
+                None => false,
+            };
+            let offered: bool = match self.seat_offers.get(&(provider_address, plan_index)) {
+                Some(offers) => offers.iter().any(|offer| offer.user == user),
+                None => false,
+            };
+            waiting || offered
+        }
+
+        /// pay_back_deposit : pays back the waitlist `deposit` of `user`
+        fn pay_back_deposit(&mut self, user: AccountId, deposit: u128) {
+            self.total_deposits = checked_sub(self.total_deposits, deposit).expect(MATH_ERROR);
+            if deposit > 0 {
+                assert_eq!(self.transfer(user, deposit), Ok(()));
+            }
+        }
+
+        /// is_in_sale_window : checks if the current time is in the sale window of `plan_index`
+        /// of `provider_address`, if `renewal` is true the window is checked only if it's limiting
+        /// renewals
+        fn is_in_sale_window(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            renewal: bool,
+        ) -> bool {
+            let window: &SaleWindow = match self.sale_windows.get(&(provider_address, plan_index)) {
+                Some(window) => window,
+                None => return true,
+            };
+            if renewal && !window.renewals {
+                return true;
+            }
+            let now: u64 = self.env().block_timestamp();
+            window.sale_start.is_none_or(|sale_start| sale_start <= now)
+                && window.sale_end.is_none_or(|sale_end| now < sale_end)
+        }
+
+        /// characteristic_rules_of : returns the rules of the characteristic at `index` of
+        /// `plan_index` of `provider_address`
+        fn characteristic_rules_of(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            index: usize,
+        ) -> CharacteristicRules {
+            match self
+                .characteristic_rules
+                .get(&(provider_address, plan_index))
+                .and_then(|plan_rules| plan_rules.get(index))
+            {
+                Some(rules) => *rules,
+                None => CharacteristicRules::default(),
+            }
+        }
+
+        /// validate_characteristics : checks `values` of the characteristics of `plan_index` of
+        /// `provider_address` against their rules, types and lengths are checked only if the values
+        /// are not encrypted which means `key_version` is 0
+        fn validate_characteristics(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            values: &[String],
+            key_version: u32,
+        ) {
+            for (index, value) in values.iter().enumerate() {
+                let rules: CharacteristicRules =
+                    self.characteristic_rules_of(provider_address, plan_index, index);
+                if rules.deprecated {
+                    assert!(
+                        value.is_empty(),
+                        "Value of deprecated characteristic must be empty"
+                    );
+                    continue;
+                }
+                if value.is_empty() {
+                    assert!(!rules.required, "Required characteristic value is empty");
+                    continue;
+                }
+                if key_version == 0 {
+                    if let Some(max_length) = rules.max_length {
+                        assert!(
+                            value.len() <= max_length as usize,
+                            "Characteristic value is too long"
+                        );
+                    }
+                    let valid: bool = match rules.value_type {
+                        CharacteristicType::Text => true,
+                        CharacteristicType::Number => value.parse::<i128>().is_ok(),
+                        CharacteristicType::Boolean => value == "true" || value == "false",
+                    };
+                    assert!(valid, "Invalid characteristic value type");
+                }
+            }
+        }
+
+        /// update_plan_stats : adds the changes of a subscription event to the counters of the
+        /// plan and the statistics of today
+        fn update_plan_stats(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            change: PlanDayStats,
+        ) {
+            let today: u64 = self.day_id_of(self.env().block_timestamp());
+            if !self
+                .plan_counters
+                .contains_key(&(provider_address, plan_index))
+            {
+                self.plan_counters
+                    .insert((provider_address, plan_index), PlanCounters::default());
+            }
+            let counters: &mut PlanCounters = self
+                .plan_counters
+                .get_mut(&(provider_address, plan_index))
+                .unwrap();
+            counters.subscriptions =
+                checked_add(counters.subscriptions, change.subscriptions).expect(MATH_ERROR);
+            counters.renewals = checked_add(counters.renewals, change.renewals).expect(MATH_ERROR);
+            counters.refunds = checked_add(counters.refunds, change.refunds).expect(MATH_ERROR);
+            counters.total_revenue =
+                checked_add(counters.total_revenue, change.revenue).expect(MATH_ERROR);
+            counters.refunded_volume =
+                checked_add(counters.refunded_volume, change.refunded_volume).expect(MATH_ERROR);
+
+            let mut day_stats: PlanDayStats =
+                self.plan_day_stats_of(provider_address, plan_index, today);
+            day_stats.subscriptions =
+                checked_add(day_stats.subscriptions, change.subscriptions).expect(MATH_ERROR);
+            day_stats.renewals =
+                checked_add(day_stats.renewals, change.renewals).expect(MATH_ERROR);
+            day_stats.refunds = checked_add(day_stats.refunds, change.refunds).expect(MATH_ERROR);
+            day_stats.revenue = checked_add(day_stats.revenue, change.revenue).expect(MATH_ERROR);
+            day_stats.refunded_volume =
+                checked_add(day_stats.refunded_volume, change.refunded_volume).expect(MATH_ERROR);
+            self.plan_day_stats
+                .insert((provider_address, plan_index, today), day_stats);
+        }
+
+        /// plan_day_stats_of : returns the statistics of `plan_index` of `provider_address` in
+        /// `day_id`
+        fn plan_day_stats_of(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            day_id: u64,
+        ) -> PlanDayStats {
+            match self
+                .plan_day_stats
+                .get(&(provider_address, plan_index, day_id))
+            {
+                Some(day_stats) => *day_stats,
+                None => PlanDayStats::default(),
+            }
+        }
+
+        /// add_expiration : records that a subscription of `plan_index` of `provider_address` ends
+        /// in `day_id`
+        fn add_expiration(&mut self, provider_address: AccountId, plan_index: u128, day_id: u64) {
+            let day_id: u64 = core::cmp::min(day_id, STATS_TREE_SIZE - 1);
+            let mut day_stats: PlanDayStats =
+                self.plan_day_stats_of(provider_address, plan_index, day_id);
+            day_stats.expirations = checked_add(day_stats.expirations, 1).expect(MATH_ERROR);
+            self.plan_day_stats
+                .insert((provider_address, plan_index, day_id), day_stats);
+
+            let mut node: u64 = day_id + 1;
+            while node <= STATS_TREE_SIZE {
+                let value: u128 =
+                    match self
+                        .expiration_tree
+                        .get(&(provider_address, plan_index, node))
+                    {
+                        Some(value) => *value,
+                        None => 0,
+                    };
+                self.expiration_tree.insert(
+                    (provider_address, plan_index, node),
+                    checked_add(value, 1).expect(MATH_ERROR),
+                );
+                node += node & node.wrapping_neg();
+            }
+        }
+
+        /// remove_expiration : removes a subscription of `plan_index` of `provider_address` that
+        /// ends in `day_id` because it's refunded or renewed
+        fn remove_expiration(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            day_id: u64,
+        ) {
+            let day_id: u64 = core::cmp::min(day_id, STATS_TREE_SIZE - 1);
+            let mut day_stats: PlanDayStats =
+                self.plan_day_stats_of(provider_address, plan_index, day_id);
+            day_stats.expirations = checked_sub(day_stats.expirations, 1).expect(MATH_ERROR);
+            if day_stats.is_empty() {
+                self.plan_day_stats
+                    .take(&(provider_address, plan_index, day_id));
+            } else {
+                self.plan_day_stats
+                    .insert((provider_address, plan_index, day_id), day_stats);
+            }
+
+            let mut node: u64 = day_id + 1;
+            while node <= STATS_TREE_SIZE {
+                let value: &mut u128 = self
+                    .expiration_tree
+                    .get_mut(&(provider_address, plan_index, node))
+                    .unwrap();
+                *value = checked_sub(*value, 1).expect(MATH_ERROR);
+                node += node & node.wrapping_neg();
+            }
+        }
+
+        /// expired_subscriptions : returns the number of subscriptions of `plan_index` of
+        /// `provider_address` that end before `day_id`
+        fn expired_subscriptions(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            day_id: u64,
+        ) -> u128 {
+            let mut sum: u128 = 0;
+            let mut node: u64 = core::cmp::min(day_id, STATS_TREE_SIZE);
+            while node > 0 {
+                if let Some(value) = self
+                    .expiration_tree
+                    .get(&(provider_address, plan_index, node))
+                {
+                    sum = checked_add(sum, *value).expect(MATH_ERROR);
+                }
+                node -= node & node.wrapping_neg();
+            }
+            sum
+        }
+
+        /// last_record : returns the last `SubscriptionRecord` of `user` in `plan_index` of
+        /// `provider_address`
+        fn last_record(
+            &self,
+            user: AccountId,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> &SubscriptionRecord {
+            let last_index: u128 = *self
                 .plan_index_to_record_index
                 .get(&(user, provider_address, plan_index))
                 .unwrap();
             let number: usize = last_index.try_into().unwrap();
-            let record: &SubscriptionRecord = &self
+            &self
                 .records
                 .get(&(user, provider_address))
                 .unwrap()
-                .subscription_records[number];
-            if record.plan_index != plan_index
-                || record.refunded
-                || record.plan.duration + record.subscription_time < self.env().block_timestamp()
+                .subscription_records[number]
+        }
+
+        /// set_username : sets `username` for `user` if `user` has no username yet
+        fn set_username(&mut self, user: AccountId, username: String) {
+            if self.address_to_username.contains_key(&user) {
+                return;
+            }
+            assert!(
+                !self.username_to_address.contains_key(&username),
+                "username is invalid!"
+            );
+            self.address_to_username.insert(user, username.clone());
+            self.username_to_address.insert(username, user);
+        }
+
+        /// create_subscription : subscribes `caller` to `plan_index` of `provider_address`
+        /// # Note
+        /// if `bundle` is `Some((bundle_id, price))` the plan is bought in that bundle and `price` is
+        /// its portion of the bundle price, otherwise the paid amount must be the price of the plan
+        fn create_subscription(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            pass: [u8; 32],
+            characteristics_values_encrypted: Vec<String>,
+            key_version: u32,
+            bundle: Option<(u32, u128)>,
+        ) {
+            let caller: AccountId = self.env().caller();
+            let time: u64 = self.env().block_timestamp();
+            self.apply_plan_change(provider_address, plan_index);
+
+            assert!(
+                !self.check_subscription(caller, provider_address, plan_index),
+                "You are already subscribed to this plan!"
+            );
+            self.take_seat(provider_address, plan_index, caller);
+
+            let provider = match self.providers.get(&provider_address) {
+                Some(provider) => provider,
+                None => panic!("Provider not existed in the contract!"),
+            };
+
+            let index: usize = plan_index.try_into().unwrap();
+
+            assert!(
+                provider.plans.len() > plan_index.try_into().unwrap(),
+                "Wrong plan index!"
+            );
+
+            let mut consts: PlanConsts = provider.plans[index];
+            assert_eq!(
+                key_version,
+                self.current_key_version(provider_address),
+                "Encryption key version is not current"
+            );
+            let plan_characteristics: Vec<String> = provider.plans_characteristics[index].clone();
+
+            assert_eq!(
+                characteristics_values_encrypted.len(),
+                plan_characteristics.len(),
+                "invalid characteristic values!"
+            );
+            match bundle {
+                Some((_, price)) => consts.price = price,
+                None => assert_eq!(
+                    consts.price,
+                    self.env().transferred_balance(),
+                    "You have to pay exact plan price"
+                ),
+            }
+            assert!(!consts.disabled, "Plan is currently disabled by provider");
+            assert!(
+                self.is_in_sale_window(provider_address, plan_index, false),
+                "Plan is not on sale"
+            );
+            self.validate_characteristics(
+                provider_address,
+                plan_index,
+                &characteristics_values_encrypted,
+                key_version,
+            );
+
+            let (provider_portion, locked_amount, dust) =
+                Self::split_price(&consts).expect(MATH_ERROR);
+            let end_day_id: u64 = self.end_day_id_of(time, consts.duration).expect(MATH_ERROR);
+
+            // send money to payees of provider (1000 - plan.max_refund_permille_policy) / 1000;
+            assert_eq!(
+                self.pay_provider(provider_address, plan_index, provider_portion),
+                Ok(())
+            );
+
+            if !self.users.contains_key(&caller) {
+                self.users.insert(
+                    caller,
+                    User {
+                        list_of_providers: Vec::new(),
+                        subscrypt_credential: Credential::from_pass(
+                            pass,
+                            self.random_salt(caller, None),
+                        ),
+                    },
+                );
+            }
+
+            let characteristics_values: Vec<String> = characteristics_values_encrypted.clone();
+            if self
+                .plan_index_to_record_index
+                .get(&(caller, provider_address, plan_index))
+                .is_none()
+            {
+                self.add_plan_subscriber(provider_address, plan_index, caller);
+            }
+
+            let subscription_record = SubscriptionRecord {
+                provider: provider_address,
+                plan: consts,
+                plan_index,
+                subscription_time: time,
+                characteristics_values_encrypted,
+                key_version,
+                plan_version: self.current_plan_version(provider_address, plan_index),
+                bundle_id: bundle.map(|(bundle_id, _)| bundle_id),
+                refunded: false,
+            };
+
+            if let Some(plan_record) = self.records.get_mut(&(caller, provider_address)) {
+                self.plan_index_to_record_index.insert(
+                    (caller, provider_address, plan_index),
+                    plan_record.subscription_records.len().try_into().unwrap(),
+                );
+
+                plan_record.subscription_records.push(subscription_record);
+            } else {
+                self.users
+                    .get_mut(&caller)
+                    .unwrap()
+                    .list_of_providers
+                    .push(provider_address);
+
+                let plan_record: PlanRecord = PlanRecord {
+                    subscription_records: vec![subscription_record],
+                    credential: Credential::from_pass(
+                        pass,
+                        self.random_salt(caller, Some(provider_address)),
+                    ),
+                };
+
+                self.records.insert((caller, provider_address), plan_record);
+
+                self.plan_index_to_record_index
+                    .insert((caller, provider_address, plan_index), 0);
+            }
+            self.add_entry(provider_address, plan_index, end_day_id, locked_amount);
+            self.treasury = checked_add(self.treasury, dust).expect(MATH_ERROR);
+            self.update_plan_stats(
+                provider_address,
+                plan_index,
+                PlanDayStats {
+                    subscriptions: 1,
+                    revenue: consts.price,
+                    ..PlanDayStats::default()
+                },
+            );
+            self.add_expiration(provider_address, plan_index, end_day_id);
+
+            self.env().emit_event(SubscribeEvent {
+                provider: provider_address,
+                plan_index,
+                user_address: caller,
+                subscription_time: time,
+                duration: consts.duration,
+                price: consts.price,
+                characteristics: characteristics_values
+            });
+        }
+
+        /// refund_subscription : refunds the subscription of `user` to `plan_index` of
+        /// `provider_address` and returns the amount that must be paid back to `user`
+        fn refund_subscription(
+            &mut self,
+            user: AccountId,
+            provider_address: AccountId,
+            plan_index: u128,
+        ) -> u128 {
+            let time: u64 = self.env().block_timestamp();
+            let last_index =
+                match self
+                    .plan_index_to_record_index
+                    .get(&(user, provider_address, plan_index))
+                {
+                    Some(index) => index,
+                    None => panic!("index is not valid!"),
+                };
+
+            let number: usize = (*last_index).try_into().unwrap();
+            let record: &SubscriptionRecord = self
+                .records
+                .get(&(user, provider_address))
+                .unwrap()
+                .subscription_records
+                .get(number)
+                .unwrap();
+
+            let elapsed_time: u64 =
+                checked_sub_time(time, record.subscription_time).expect(MATH_ERROR);
+            assert!(elapsed_time < record.plan.duration);
+
+            // these amounts are multiplied by 1000 to keep the precision of the linear refund
+            let promised_amount: u128 =
+                checked_mul(record.plan.price, record.plan.max_refund_permille_policy)
+                    .expect(MATH_ERROR);
+            let price: u128 = checked_mul(record.plan.price, 1000).expect(MATH_ERROR);
+            let used: u128 = checked_mul(price, u128::from(elapsed_time))
+                .and_then(|x| checked_div(x, u128::from(record.plan.duration)))
+                .expect(MATH_ERROR);
+            let mut customer_portion_locked_money: u128 =
+                checked_sub(price, used).expect(MATH_ERROR);
+
+            if customer_portion_locked_money > promised_amount {
+                // in this case the customer wants to refund very early so he want to get
+                // more than the amount of refund policy, so we can only give back just
+                // max_refund_permille_policy of his/her subscription. Whole locked money will go directly to
+                // account of the customer
+
+                customer_portion_locked_money = promised_amount;
+            }
+            // otherwise the customer wants to refund, but he/she used most of his subscription time
+            // and now he/she will get portion of locked money, and the provider will get the rest of money
+            let refunded_amount: u128 = customer_portion_locked_money / 1000;
+            let locked_amount: u128 = promised_amount / 1000;
+            let provider_portion_locked_money: u128 =
+                checked_sub(locked_amount, refunded_amount).expect(MATH_ERROR);
+            if provider_portion_locked_money > 0 {
+                assert_eq!(
+                    self.pay_provider(provider_address, plan_index, provider_portion_locked_money),
+                    Ok(())
+                );
+            }
+
+            let passed_day_id: u64 = self
+                .end_day_id_of(record.subscription_time, record.plan.duration)
+                .expect(MATH_ERROR);
+            self.remove_entry(
+                provider_address,
+                plan_index,
+                passed_day_id,
+                locked_amount,
+                refunded_amount,
+            );
+            self.records
+                .get_mut(&(user, provider_address))
+                .unwrap()
+                .subscription_records
+                .get_mut(number)
+                .unwrap()
+                .refunded = true;
+            self.update_plan_stats(
+                provider_address,
+                plan_index,
+                PlanDayStats {
+                    refunds: 1,
+                    refunded_volume: refunded_amount,
+                    ..PlanDayStats::default()
+                },
+            );
+            self.remove_expiration(provider_address, plan_index, passed_day_id);
+            self.update_waitlist(provider_address, plan_index);
+            refunded_amount
+        }
+
+        /// ecdsa_recover : returns the compressed ECDSA public key that has signed `message_hash`
+        /// with the 65 bytes `signature` or `None` if the signature is not valid
+        /// # Note
+        /// `ink_env` has no ECDSA recovery, so `r^-1 (s R - z G)` is computed here with plain
+        /// double-and-add, which needs no precomputed tables of `G`. Signatures whose `R.x` is
+        /// bigger than the curve order (recovery ids 2 and 3) are rejected.
+        fn ecdsa_recover(signature: &[u8; 65], message_hash: &[u8; 32]) -> Option<[u8; 33]> {
+            let recovery_id: u8 = if signature[64] >= 27 {
+                signature[64] - 27
+            } else {
+                signature[64]
+            };
+            if recovery_id > 1 {
+                return None;
+            }
+            let mut r_bytes = [0u8; 32];
+            let mut s_bytes = [0u8; 32];
+            r_bytes.copy_from_slice(&signature[..32]);
+            s_bytes.copy_from_slice(&signature[32..64]);
+
+            let mut sig_r = Scalar::default();
+            let mut sig_s = Scalar::default();
+            if bool::from(sig_r.set_b32(&r_bytes))
+                || bool::from(sig_s.set_b32(&s_bytes))
+                || sig_r.is_zero()
+                || sig_s.is_zero()
             {
-                panic!("user doesn't have this plan!");
+                return None;
+            }
+            let mut message = Scalar::default();
+            let _ = message.set_b32(message_hash);
+
+            let mut r_x = Field::default();
+            let mut point_r = Affine::default();
+            if !r_x.set_b32(&r_bytes) || !point_r.set_xo_var(&r_x, recovery_id == 1) {
+                return None;
+            }
+            let r_inv = sig_r.inv();
+            let u1 = -(r_inv * message);
+            let u2 = r_inv * sig_s;
+
+            let mut point_q = Jacobian::default();
+            point_q.set_infinity();
+            for bit in (0..256).rev() {
+                point_q = point_q.double_var(None);
+                if u2.bits(bit, 1) == 1 {
+                    point_q = point_q.add_ge_var(&point_r, None);
+                }
+                if u1.bits(bit, 1) == 1 {
+                    point_q = point_q.add_ge_var(&AFFINE_G, None);
+                }
+            }
+            let mut public_key = Affine::default();
+            public_key.set_gej_var(&point_q);
+            if public_key.is_infinity() {
+                return None;
+            }
+            public_key.x.normalize_var();
+            public_key.y.normalize_var();
+            let mut compressed = [0u8; 33];
+            compressed[0] = if public_key.y.is_odd() { 3 } else { 2 };
+            compressed[1..].copy_from_slice(&public_key.x.b32());
+            Some(compressed)
+        }
+
+        /// resolve_user : returns the address of `user` or `None` if the username is unknown
+        fn resolve_user(&self, user: &UserIdentifier) -> Option<AccountId> {
+            match user {
+                UserIdentifier::Address(address) => Some(*address),
+                UserIdentifier::Username(username) => {
+                    self.username_to_address.get(username).copied()
+                }
             }
-            record.characteristics_values_encrypted.clone()
         }
 
-        /// We can get plan count in this function
-        ///
-        /// # Returns
-        /// a number is returned
-        ///
-        /// # Example
-        /// Examples in `tests/test.rs`
-        #[ink(message)]
-        pub fn get_plan_length(&self, provider_address: AccountId) -> u128 {
-            match self.providers.get(&provider_address) {
-                Some(provider) => provider.plans.len().try_into().unwrap(),
-                None => 0,
+        /// subscription_status_of : returns the status of `record` at the current block
+        fn subscription_status_of(&self, record: &SubscriptionRecord) -> SubscriptionStatus {
+            let now: u64 = self.env().block_timestamp();
+            if record.refunded {
+                SubscriptionStatus::Refunded
+            } else if checked_add_time(record.plan.duration, record.subscription_time)
+                .expect(MATH_ERROR)
+                < now
+            {
+                SubscriptionStatus::Expired
+            } else if record.subscription_time > now {
+                SubscriptionStatus::Scheduled
+            } else if self
+                .plan_consts_of(record.provider, record.plan_index)
+                .disabled
+            {
+                SubscriptionStatus::Paused
+            } else {
+                SubscriptionStatus::Active
             }
         }
 
-        /// We can get plan characteristic keys in this function
-        ///
-        /// # Returns
-        /// `Vec<String>` is returned
-        ///
-        /// # Example
-        /// Examples in `tests/test.rs`
-        #[ink(message)]
-        pub fn get_plan_characteristics(
+        /// subscription_info_of : returns the status of the subscription of `user` to
+        /// `plan_index` of `provider_address` or `None` if `user` has never subscribed to it
+        fn subscription_info_of(
             &self,
+            user: AccountId,
             provider_address: AccountId,
             plan_index: u128,
-        ) -> Vec<String> {
-            let number: usize = plan_index.try_into().unwrap();
-            match match self.providers.get(&provider_address) {
-                Some(provider) => provider,
-                None => panic!("index is not valid!"),
-            }
-            .plans_characteristics
-            .get(number)
+        ) -> Option<SubscriptionInfo> {
+            let last_index: u128 =
+                *self
+                    .plan_index_to_record_index
+                    .get(&(user, provider_address, plan_index))?;
+            let number: usize = last_index.try_into().unwrap();
+            let records: &Vec<SubscriptionRecord> = &self
+                .records
+                .get(&(user, provider_address))
+                .unwrap()
+                .subscription_records;
+            let now: u64 = self.env().block_timestamp();
+
+            let last: &SubscriptionRecord = &records[number];
+            let mut current: &SubscriptionRecord = last;
+            let mut queued_renewals: u32 = 0;
+            for record in records[..number]
+                .iter()
+                .rev()
+                .filter(|record| record.plan_index == plan_index)
             {
-                Some(x) => x.clone(),
-                None => panic!("please select a valid plan"),
+                if current.subscription_time <= now {
+                    break;
+                }
+                current = record;
+                queued_renewals += 1;
             }
+            let status: SubscriptionStatus = if last.refunded {
+                SubscriptionStatus::Refunded
+            } else {
+                self.subscription_status_of(current)
+            };
+            Some(SubscriptionInfo {
+                status,
+                start: current.subscription_time,
+                end: checked_add_time(last.subscription_time, last.plan.duration)
+                    .expect(MATH_ERROR),
+                queued_renewals,
+                next_renewal_price: self.plan_consts_of(provider_address, plan_index).price,
+            })
         }
 
-        /// Get hash of String
-        ///
-        /// # Returns
-        /// `String` is returned
-        ///    
-        #[ink(message)]
-        pub fn get_sha2(&self, string: String) -> [u8; 32] {
-            let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
-            ink_env::hash_encoded::<Sha2x256, _>(&string, &mut output);
-            return output;
-        }
-
-        /// This function can be called to check if `user` has a valid subscription to the
-        /// specific `plan_index` of `provider`.
-        ///
-        /// # Note
-        /// if `user` refunded or her subscription is expired then this function will return false
-        ///
-        /// # Returns
-        /// `bool` which means if `user` is subscribed or not
-        ///
-        /// # Example
-        /// Examples in `check_subscription_works` in `tests/test.rs`
-        #[ink(message)]
-        pub fn check_subscription(
+        /// active_record : returns the last `SubscriptionRecord` of `user` in `plan_index` of
+        /// `provider_address` if it's not refunded or expired
+        fn active_record(
             &self,
             user: AccountId,
             provider_address: AccountId,
             plan_index: u128,
-        ) -> bool {
-            if !self
-                .plan_index_to_record_index
-                .contains_key(&(user, provider_address, plan_index))
-            {
-                return false;
-            }
-            let last_index: u128 = *self
-                .plan_index_to_record_index
-                .get(&(user, provider_address, plan_index))
-                .unwrap();
+        ) -> Option<&SubscriptionRecord> {
+            let last_index: u128 =
+                *self
+                    .plan_index_to_record_index
+                    .get(&(user, provider_address, plan_index))?;
             let number: usize = last_index.try_into().unwrap();
             let record: &SubscriptionRecord = &self
                 .records
@@ -1379,72 +5905,56 @@ pub mod subscrypt {
                 .subscription_records[number];
             if record.plan_index != plan_index
                 || record.refunded
-                || record.plan.duration + record.subscription_time < self.env().block_timestamp()
+                || checked_add_time(record.plan.duration, record.subscription_time)
+                    .expect(MATH_ERROR)
+                    < self.env().block_timestamp()
             {
-                return false;
+                return None;
             }
-            true
+            Some(record)
         }
 
-        /// This function can be called to check if `user` has a valid subscription to the
-        /// specific `plan_index` of `provider`.
-        ///
-        /// # Note
-        /// if `user` refunded or her subscription is expired then this function will return false
-        ///
-        /// # Returns
-        /// `bool` which means if `user` is subscribed or not
-        ///
-        /// # Example
-        /// Examples in `check_subscription_works` in `tests/test.rs`
-        #[ink(message)]
-        pub fn check_subscription_with_username(
+        /// has_read_access : checks if `account` is `user` or has an unexpired read grant of
+        /// `user` for `provider_address`
+        fn has_read_access(
             &self,
-            username: String,
+            user: AccountId,
             provider_address: AccountId,
-            plan_index: u128,
+            account: AccountId,
         ) -> bool {
-            match self.username_to_address.get(&username) {
-                Some(name) => self.check_subscription(*name, provider_address, plan_index),
+            if account == user {
+                return true;
+            }
+            let now: u64 = self.env().block_timestamp();
+            match self.read_grants.get(&user) {
+                Some(grants) => grants.iter().any(|grant| {
+                    grant.grantee == account
+                        && grant.expires_at > now
+                        && (grant.provider.is_none() || grant.provider == Some(provider_address))
+                }),
                 None => false,
             }
         }
 
-        fn retrieve_whole_data(&self, caller: AccountId) -> Vec<SubscriptionRecord> {
-            assert!(self.users.contains_key(&caller));
-            let mut data: Vec<SubscriptionRecord> = Vec::new();
-            let user: &User = self.users.get(&caller).unwrap();
-            for i in 0..user.list_of_providers.len() {
-                data.append(&mut self.retrieve_data(caller, user.list_of_providers[i]));
+        /// is_provider_or_operator : checks if `account` is `provider_address` or one of its operators
+        fn is_provider_or_operator(&self, provider_address: AccountId, account: AccountId) -> bool {
+            match self.providers.get(&provider_address) {
+                Some(provider) => {
+                    account == provider_address || provider.operators.contains(&account)
+                }
+                None => false,
             }
-            data
         }
 
-        fn retrieve_data(
-            &self,
-            caller: AccountId,
-            provider_address: AccountId,
-        ) -> Vec<SubscriptionRecord> {
-            assert!(self.users.contains_key(&caller));
-            assert!(self.records.contains_key(&(caller, provider_address)));
-            let mut data: Vec<SubscriptionRecord> = Vec::new();
-
-            let plan_records: &PlanRecord = self.records.get(&(caller, provider_address)).unwrap();
-            for i in 0..plan_records.subscription_records.len() {
-                let k = SubscriptionRecord {
-                    provider: plan_records.subscription_records[i].provider,
-                    plan: plan_records.subscription_records[i].plan,
-                    plan_index: plan_records.subscription_records[i].plan_index,
-                    subscription_time: plan_records.subscription_records[i].subscription_time,
-                    characteristics_values_encrypted: plan_records.subscription_records[i]
-                        .characteristics_values_encrypted
-                        .clone(),
-
-                    refunded: plan_records.subscription_records[i].refunded,
-                };
-                data.push(k);
-            }
-            data
+        fn assert_provider_access(&self, provider_address: AccountId) {
+            assert!(
+                self.providers.contains_key(&provider_address),
+                "provider address is not valid!"
+            );
+            assert!(
+                self.is_provider_or_operator(provider_address, self.env().caller()),
+                "You are not the provider or its operator"
+            );
         }
 
         fn transfer(&self, addr: AccountId, amount: u128) -> Result<(), Error> {
@@ -1454,164 +5964,389 @@ pub mod subscrypt {
             })
         }
 
-        /// add_entry : add a payment entry to provider payment management linked list
+        /// pay_provider : sends earned money of provider to the payees of `plan_index`
+        /// # arguments:
+        /// * provider_address
+        /// * plan_index : payout table of this plan is used if it is set, otherwise the provider one
+        /// * amount : money amount
+        fn pay_provider(
+            &self,
+            provider_address: AccountId,
+            plan_index: u128,
+            amount: u128,
+        ) -> Result<(), Error> {
+            let provider: &Provider = self.providers.get(&provider_address).unwrap();
+            let number: usize = plan_index.try_into().unwrap();
+            self.split_payment(
+                Self::payout_table_of(provider, number),
+                provider.money_address,
+                amount,
+            )
+        }
+
+        /// split_payment : splits `amount` between the accounts of `payout_table` based on their shares
+        /// # Note
+        /// The rounding dust is paid to the first account of the table and if the table is empty
+        /// the whole amount is sent to `default_address`
+        fn split_payment(
+            &self,
+            payout_table: &[PayoutShare],
+            default_address: AccountId,
+            amount: u128,
+        ) -> Result<(), Error> {
+            if payout_table.is_empty() {
+                return self.transfer(default_address, amount);
+            }
+            let mut shares: Vec<u128> = Vec::new();
+            let mut paid_amount: u128 = 0;
+            for payee in payout_table.iter() {
+                let share: u128 = permille_of(amount, payee.share_permille).expect(MATH_ERROR);
+                paid_amount = checked_add(paid_amount, share).expect(MATH_ERROR);
+                shares.push(share);
+            }
+            shares[0] = checked_sub(amount, paid_amount)
+                .and_then(|dust| checked_add(shares[0], dust))
+                .expect(MATH_ERROR);
+            for i in 0..payout_table.len() {
+                if shares[i] > 0 {
+                    self.transfer(payout_table[i].account, shares[i])?;
+                }
+            }
+            Ok(())
+        }
+
+        /// payout_table_of : returns the payout table that is used for payments of plan `number`
+        fn payout_table_of(provider: &Provider, number: usize) -> &[PayoutShare] {
+            match provider.plans_payout_tables.get(number) {
+                Some(payout_table) if !payout_table.is_empty() => payout_table.as_slice(),
+                _ => provider.payout_table.as_slice(),
+            }
+        }
+
+        fn build_payout_table(
+            accounts: Vec<AccountId>,
+            shares_permille: Vec<u128>,
+        ) -> Vec<PayoutShare> {
+            assert_eq!(
+                accounts.len(),
+                shares_permille.len(),
+                "Wrong Number of Args"
+            );
+            let mut payout_table: Vec<PayoutShare> = Vec::new();
+            let mut sum: u128 = 0;
+            for i in 0..accounts.len() {
+                assert!(shares_permille[i] <= 1000, "Sum of shares must be 1000");
+                sum = checked_add(sum, shares_permille[i]).expect(MATH_ERROR);
+                payout_table.push(PayoutShare {
+                    account: accounts[i],
+                    share_permille: shares_permille[i],
+                });
+            }
+            assert!(
+                payout_table.is_empty() || sum == 1000,
+                "Sum of shares must be 1000"
+            );
+            payout_table
+        }
+
+        /// day_id_of : returns the id of the day that `time` is in
+        /// # Note
+        /// the calculation formula is : (time - contract start date) / 86400
+        fn day_id_of(&self, time: u64) -> u64 {
+            checked_sub_time(time, self.start_time).expect(MATH_ERROR) / 86400
+        }
+
+        /// end_day_id_of : returns the id of the day that a subscription which is started at
+        /// `subscription_time` with `duration` ends in
+        fn end_day_id_of(
+            &self,
+            subscription_time: u64,
+            duration: u64,
+        ) -> Result<u64, ArithmeticError> {
+            let end_time: u64 = checked_add_time(subscription_time, duration)?;
+            Ok(checked_sub_time(end_time, self.start_time)? / 86400)
+        }
+
+        /// split_price : splits the `price` of `plan` to the portion that is paid to provider
+        /// immediately, the portion that is locked for refund policy and the rounding dust
+        fn split_price(plan: &PlanConsts) -> Result<(u128, u128, u128), ArithmeticError> {
+            let provider_portion: u128 = permille_of(
+                plan.price,
+                checked_sub(1000, plan.max_refund_permille_policy)?,
+            )?;
+            let locked_amount: u128 = permille_of(plan.price, plan.max_refund_permille_policy)?;
+            let dust: u128 =
+                checked_sub(checked_sub(plan.price, provider_portion)?, locked_amount)?;
+            Ok((provider_portion, locked_amount, dust))
+        }
+
+        /// add_entry : add a payment entry to the bucket of `day_id` of provider escrow
         /// # arguments:
         /// * provider_address
+        /// * plan_index : the plan that the money is paid for
         /// * day_id : the calculation formula is : (finish date - contract start date) / 86400
         /// * amount : money amount
-        fn add_entry(&mut self, provider_address: AccountId, day_id: u64, amount: u128) {
-            let linked_list: &mut LinkedList = &mut self
+        pub(crate) fn add_entry(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            day_id: u64,
+            amount: u128,
+        ) {
+            if amount == 0 {
+                return;
+            }
+            let escrow_index: &mut EscrowIndex = &mut self
                 .providers
                 .get_mut(&provider_address)
                 .unwrap()
                 .payment_manager;
-            if linked_list.length == 0 {
-                let object = DailyLockedAmount {
-                    amount,
-                    next_day: day_id,
-                };
-                linked_list.head = day_id;
-                self.daily_locked_amounts
-                    .insert((provider_address, day_id), object);
-                linked_list.back = day_id;
-                linked_list.length += 1;
-            } else if day_id < linked_list.head {
-                let object = DailyLockedAmount {
-                    amount,
-                    next_day: linked_list.head,
+            let locked_amount: u128 =
+                match self.daily_locked_amounts.get(&(provider_address, day_id)) {
+                    Some(x) => *x,
+                    None => 0,
                 };
-                linked_list.head = day_id;
-                self.daily_locked_amounts
-                    .insert((provider_address, day_id), object);
-                linked_list.length += 1;
-            } else if day_id > linked_list.back {
-                self.daily_locked_amounts
-                    .get_mut(&(provider_address, linked_list.back))
-                    .unwrap()
-                    .next_day = day_id;
-                let object = DailyLockedAmount {
-                    amount,
-                    next_day: day_id,
+            if locked_amount == 0 {
+                let word_id = day_id / BUCKETS_PER_WORD;
+                let word: u128 = match self.escrow_bitmaps.get(&(provider_address, word_id)) {
+                    Some(x) => *x,
+                    None => 0,
                 };
-                linked_list.back = day_id;
-                self.daily_locked_amounts
-                    .insert((provider_address, day_id), object);
-                linked_list.length += 1;
-            } else {
-                let mut cur_id: u64 = linked_list.head;
-                loop {
-                    if day_id == cur_id {
-                        self.daily_locked_amounts
-                            .get_mut(&(provider_address, day_id))
-                            .unwrap()
-                            .amount += amount;
-                        break;
-                    } else if day_id
-                        < self
-                            .daily_locked_amounts
-                            .get(&(provider_address, cur_id))
-                            .unwrap()
-                            .next_day
-                    {
-                        let object = DailyLockedAmount {
-                            amount,
-                            next_day: self
-                                .daily_locked_amounts
-                                .get(&(provider_address, cur_id))
-                                .unwrap()
-                                .next_day,
-                        };
-                        self.daily_locked_amounts
-                            .get_mut(&(provider_address, cur_id))
-                            .unwrap()
-                            .next_day = day_id;
-                        self.daily_locked_amounts
-                            .insert((provider_address, day_id), object);
-                        linked_list.length += 1;
-                        break;
-                    }
-                    cur_id = self
-                        .daily_locked_amounts
-                        .get(&(provider_address, cur_id))
-                        .unwrap()
-                        .next_day;
-                    if cur_id == linked_list.back {
-                        break;
-                    }
+                self.escrow_bitmaps.insert(
+                    (provider_address, word_id),
+                    word | (1u128 << (day_id % BUCKETS_PER_WORD)),
+                );
+                escrow_index.length = checked_add(escrow_index.length, 1).expect(MATH_ERROR);
+            }
+            if day_id > escrow_index.back {
+                escrow_index.back = day_id;
+            }
+            escrow_index.total_locked =
+                checked_add(escrow_index.total_locked, amount).expect(MATH_ERROR);
+            self.total_escrowed = checked_add(self.total_escrowed, amount).expect(MATH_ERROR);
+            self.daily_locked_amounts.insert(
+                (provider_address, day_id),
+                checked_add(locked_amount, amount).expect(MATH_ERROR),
+            );
+            let mut plan_amounts: Vec<(u128, u128)> = self
+                .daily_plan_locked_amounts
+                .take(&(provider_address, day_id))
+                .unwrap_or_default();
+            match plan_amounts
+                .iter_mut()
+                .find(|(index, _)| *index == plan_index)
+            {
+                Some((_, plan_amount)) => {
+                    *plan_amount = checked_add(*plan_amount, amount).expect(MATH_ERROR)
                 }
+                None => plan_amounts.push((plan_index, amount)),
             }
+            self.daily_plan_locked_amounts
+                .insert((provider_address, day_id), plan_amounts);
         }
 
         /// remove_entry : when a user refunds this function removes its related entry
         /// # arguments:
         /// * provider_address
+        /// * plan_index : the plan that the money is paid for
         /// * day_id : the calculation formula is : (finish date - contract start date) / 86400
         /// * amount
-        fn remove_entry(&mut self, provider_address: AccountId, day_id: u64, amount: u128) {
-            self.daily_locked_amounts
-                .get_mut(&(provider_address, day_id))
+        /// * refunded_amount : portion of `amount` that is paid back to the user, the rest is paid to provider
+        fn remove_entry(
+            &mut self,
+            provider_address: AccountId,
+            plan_index: u128,
+            day_id: u64,
+            amount: u128,
+            refunded_amount: u128,
+        ) {
+            if amount == 0 {
+                return;
+            }
+            let escrow_index: &mut EscrowIndex = &mut self
+                .providers
+                .get_mut(&provider_address)
+                .unwrap()
+                .payment_manager;
+            escrow_index.total_locked =
+                checked_sub(escrow_index.total_locked, amount).expect(MATH_ERROR);
+            escrow_index.total_refunded =
+                checked_add(escrow_index.total_refunded, refunded_amount).expect(MATH_ERROR);
+            escrow_index.total_withdrawn = checked_sub(amount, refunded_amount)
+                .and_then(|x| checked_add(escrow_index.total_withdrawn, x))
+                .expect(MATH_ERROR);
+            self.total_escrowed = checked_sub(self.total_escrowed, amount).expect(MATH_ERROR);
+            let locked_amount: u128 = checked_sub(
+                *self
+                    .daily_locked_amounts
+                    .get(&(provider_address, day_id))
+                    .unwrap(),
+                amount,
+            )
+            .expect(MATH_ERROR);
+            if locked_amount > 0 {
+                self.daily_locked_amounts
+                    .insert((provider_address, day_id), locked_amount);
+                let plan_amounts: &mut Vec<(u128, u128)> = self
+                    .daily_plan_locked_amounts
+                    .get_mut(&(provider_address, day_id))
+                    .unwrap();
+                let position: usize = plan_amounts
+                    .iter()
+                    .position(|(index, _)| *index == plan_index)
+                    .unwrap();
+                plan_amounts[position].1 =
+                    checked_sub(plan_amounts[position].1, amount).expect(MATH_ERROR);
+                if plan_amounts[position].1 == 0 {
+                    plan_amounts.swap_remove(position);
+                }
+            } else {
+                self.clear_bucket(provider_address, day_id);
+                let escrow_index: &mut EscrowIndex = &mut self
+                    .providers
+                    .get_mut(&provider_address)
+                    .unwrap()
+                    .payment_manager;
+                escrow_index.length = checked_sub(escrow_index.length, 1).expect(MATH_ERROR);
+            }
+        }
+
+        /// clear_bucket : removes the bucket of `day_id` and its bit in `escrow_bitmaps`
+        fn clear_bucket(&mut self, provider_address: AccountId, day_id: u64) {
+            self.daily_locked_amounts.take(&(provider_address, day_id));
+            self.daily_plan_locked_amounts
+                .take(&(provider_address, day_id));
+            let word_id = day_id / BUCKETS_PER_WORD;
+            let word: u128 = *self
+                .escrow_bitmaps
+                .get(&(provider_address, word_id))
                 .unwrap()
-                .amount -= amount;
+                & !(1u128 << (day_id % BUCKETS_PER_WORD));
+            if word == 0 {
+                self.escrow_bitmaps.take(&(provider_address, word_id));
+            } else {
+                self.escrow_bitmaps
+                    .insert((provider_address, word_id), word);
+            }
         }
 
         /// process : when providers withdraw this function calculates the amount of money
         /// # arguments:
         /// * provider_address
-        /// * day_id : the calculation formula is : (finish date - contract start date) / 86400
-        pub fn process(
+        /// * day_id : locked money of the days before this day is withdrawable
+        /// * max_buckets : maximum number of non-empty buckets that are processed plus the empty
+        /// words of the bitmap that are skipped
+        fn process(
             &self,
             provider_address: AccountId,
             day_id: u64,
+            max_buckets: u128,
         ) -> ProcessReturningData {
-            let linked_list: &LinkedList = &self
+            let escrow_index: &EscrowIndex = &self
                 .providers
                 .get(&provider_address)
                 .unwrap()
                 .payment_manager;
+            let last_id: u64 = core::cmp::min(day_id, escrow_index.back + 1);
             let mut sum: u128 = 0;
-            let mut reduced_length = 0;
-            let mut cur_id: u64 = linked_list.head;
-            while day_id >= cur_id {
-                sum += self
-                    .daily_locked_amounts
-                    .get(&(provider_address, cur_id))
-                    .unwrap()
-                    .amount;
-                cur_id = self
-                    .daily_locked_amounts
-                    .get(&(provider_address, cur_id))
-                    .unwrap()
-                    .next_day;
-                reduced_length += 1;
-                if cur_id == linked_list.back {
-                    break;
+            let mut processed_days: Vec<u64> = Vec::new();
+            let mut plan_amounts: Vec<(u128, u128)> = Vec::new();
+            let mut processed_buckets: u128 = 0;
+            let mut empty_words: u128 = 0;
+            let mut cur_id: u64 = escrow_index.cursor;
+            'words: while cur_id < last_id && processed_buckets + empty_words < max_buckets {
+                let word_id = cur_id / BUCKETS_PER_WORD;
+                let mut remaining_days: u128 =
+                    match self.escrow_bitmaps.get(&(provider_address, word_id)) {
+                        Some(word) => *word >> (cur_id % BUCKETS_PER_WORD),
+                        None => 0,
+                    };
+                if remaining_days == 0 {
+                    empty_words += 1;
+                }
+                while remaining_days != 0 {
+                    let offset = remaining_days.trailing_zeros();
+                    cur_id += u64::from(offset);
+                    if cur_id >= last_id || processed_buckets + empty_words >= max_buckets {
+                        break 'words;
+                    }
+                    sum = checked_add(
+                        sum,
+                        *self
+                            .daily_locked_amounts
+                            .get(&(provider_address, cur_id))
+                            .unwrap(),
+                    )
+                    .expect(MATH_ERROR);
+                    for (plan_index, amount) in self
+                        .daily_plan_locked_amounts
+                        .get(&(provider_address, cur_id))
+                        .unwrap()
+                        .iter()
+                    {
+                        match plan_amounts
+                            .iter_mut()
+                            .find(|(index, _)| index == plan_index)
+                        {
+                            Some((_, plan_amount)) => {
+                                *plan_amount = checked_add(*plan_amount, *amount).expect(MATH_ERROR)
+                            }
+                            None => plan_amounts.push((*plan_index, *amount)),
+                        }
+                    }
+                    processed_days.push(cur_id);
+                    processed_buckets += 1;
+                    cur_id += 1;
+                    remaining_days = (remaining_days >> offset) >> 1;
                 }
+                cur_id = (word_id + 1) * BUCKETS_PER_WORD;
             }
 
             ProcessReturningData {
                 withdrawing_amount: sum,
-                current_linked_list_head: cur_id,
-                reduced_length,
+                next_cursor: core::cmp::max(escrow_index.cursor, core::cmp::min(cur_id, last_id)),
+                processed_days,
+                plan_amounts,
             }
         }
-    }
 
-    impl Default for LinkedList {
-        fn default() -> Self {
-            Self::new()
+        /// release_escrow : removes the buckets that `process` calculates from provider escrow
+        /// # arguments:
+        /// * provider_address
+        /// * day_id : locked money of the days before this day is withdrawable
+        /// * max_buckets : maximum number of non-empty buckets that are processed plus the empty
+        /// words of the bitmap that are skipped
+        pub(crate) fn release_escrow(
+            &mut self,
+            provider_address: AccountId,
+            day_id: u64,
+            max_buckets: u128,
+        ) -> ProcessReturningData {
+            let t = self.process(provider_address, day_id, max_buckets);
+            for i in 0..t.processed_days.len() {
+                self.clear_bucket(provider_address, t.processed_days[i]);
+            }
+            let escrow_index: &mut EscrowIndex = &mut self
+                .providers
+                .get_mut(&provider_address)
+                .unwrap()
+                .payment_manager;
+            escrow_index.length =
+                checked_sub(escrow_index.length, t.processed_days.len() as u128).expect(MATH_ERROR);
+            escrow_index.cursor = t.next_cursor;
+            escrow_index.total_locked =
+                checked_sub(escrow_index.total_locked, t.withdrawing_amount).expect(MATH_ERROR);
+            escrow_index.total_withdrawn =
+                checked_add(escrow_index.total_withdrawn, t.withdrawing_amount).expect(MATH_ERROR);
+            self.total_escrowed =
+                checked_sub(self.total_escrowed, t.withdrawing_amount).expect(MATH_ERROR);
+            t
         }
     }
 
-    impl LinkedList {
+    impl EscrowIndex {
         pub fn new() -> Self {
-            LinkedList::default()
-        }
-        pub fn default() -> Self {
-            Self {
-                back: 0,
-                head: 0,
-                length: 0,
-            }
+            EscrowIndex::default()
         }
     }
 }
@@ -7,11 +7,36 @@ mod utils;
 
 #[cfg(test)]
 pub mod tests {
-    use crate::subscrypt::subscrypt::LinkedList;
+    use crate::subscrypt::subscrypt::AccessToken;
+    use crate::subscrypt::subscrypt::Bundle;
+    use crate::subscrypt::subscrypt::Characteristic;
+    use crate::subscrypt::subscrypt::CharacteristicRules;
+    use crate::subscrypt::subscrypt::CharacteristicType;
+    use crate::subscrypt::subscrypt::Credential;
+    use crate::subscrypt::subscrypt::EscrowIndex;
+    use crate::subscrypt::subscrypt::EscrowStatement;
+    use crate::subscrypt::subscrypt::HashAlgorithm;
+    use crate::subscrypt::subscrypt::PayoutShare;
+    use crate::subscrypt::subscrypt::PlanCapacity;
+    use crate::subscrypt::subscrypt::PlanChange;
     use crate::subscrypt::subscrypt::PlanConsts;
+    use crate::subscrypt::subscrypt::PlanVersion;
+    use crate::subscrypt::subscrypt::ProviderProfile;
+    use crate::subscrypt::subscrypt::ReadGrant;
+    use crate::subscrypt::subscrypt::RecordCursor;
+    use crate::subscrypt::subscrypt::RecordFilter;
+    use crate::subscrypt::subscrypt::RecordSummary;
+    use crate::subscrypt::subscrypt::SaleWindow;
+    use crate::subscrypt::subscrypt::SeatOffer;
+    use crate::subscrypt::subscrypt::SubscriberRow;
+    use crate::subscrypt::subscrypt::SubscriptionInfo;
+    use crate::subscrypt::subscrypt::SubscriptionStatus;
     use crate::subscrypt::subscrypt::Subscrypt;
+    use crate::subscrypt::subscrypt::UserIdentifier;
+    use crate::subscrypt::subscrypt::WaitlistEntry;
     use crate::utils::utils::{
-        set_account_balance, set_caller, subscrypt_add_plan_routine, subscrypt_edit_plan_routine,
+        advance_time, login_public_key, set_account_balance, set_caller, set_caller_and_pay,
+        sign_login_message, subscrypt_add_plan_routine, subscrypt_edit_plan_routine,
         subscrypt_provider_register_routine,
     };
     use ink_env::hash::{HashOutput, Sha2x256};
@@ -30,15 +55,15 @@ pub mod tests {
     }
 
     #[ink::test]
-    fn linked_list_works() {
-        let linked = LinkedList::new();
-        assert_eq!(linked.back, 0);
+    fn escrow_index_works() {
+        let escrow_index = EscrowIndex::new();
+        assert_eq!(escrow_index.back, 0);
     }
 
     #[ink::test]
-    fn linked_list_default_works() {
-        let linked = LinkedList::default();
-        assert_eq!(linked.back, 0);
+    fn escrow_index_default_works() {
+        let escrow_index = EscrowIndex::default();
+        assert_eq!(escrow_index.cursor, 0);
     }
 
     /// Simple scenario that `alice` register as a provider
@@ -356,6 +381,7 @@ pub mod tests {
             output,
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -415,6 +441,7 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -458,6 +485,7 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -474,6 +502,116 @@ pub mod tests {
         );
     }
 
+    /// Simple scenario that `bob` and `charlie` subscribe to `alice` and `alice` gets their plan
+    /// characteristics page by page, then `bob` refunds and is not returned anymore
+    #[ink::test]
+    fn get_plan_subscribers_characteristics_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+
+        set_account_balance(callee, 100100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            subscrypt.get_sha2("pass_phrase".to_string()),
+            "bob".to_string(),
+            vec!["bob value".to_string()],
+            0,
+        );
+        set_caller(callee, accounts.charlie, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "charlie".to_string(),
+            vec!["charlie value".to_string()],
+            0,
+        );
+        assert_eq!(
+            subscrypt.get_user_plan_characteristics_with_username(
+                "bob".to_string(),
+                accounts.alice,
+                1,
+                "pass_phrase".to_string()
+            ),
+            vec!["bob value".to_string()]
+        );
+
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(
+            subscrypt.get_user_plan_characteristics(accounts.charlie, accounts.alice, 1),
+            vec!["charlie value".to_string()]
+        );
+        assert_eq!(
+            subscrypt.get_plan_subscribers_characteristics(accounts.alice, 1, 0, 1),
+            vec![(accounts.bob, vec!["bob value".to_string()])]
+        );
+        assert_eq!(
+            subscrypt.get_plan_subscribers_characteristics(accounts.alice, 1, 1, 10),
+            vec![(accounts.charlie, vec!["charlie value".to_string()])]
+        );
+
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.refund(accounts.alice, 1);
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(
+            subscrypt.get_plan_subscribers_characteristics(accounts.alice, 1, 0, 10),
+            vec![(accounts.charlie, vec!["charlie value".to_string()])]
+        );
+    }
+
+    /// Simple scenario that `charlie` tries to get the plan characteristics of `bob` without
+    /// any access, so it will fail
+    #[ink::test]
+    #[should_panic(expected = "You don't have access to this data")]
+    fn get_user_plan_characteristics_fails_no_access() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.get_user_plan_characteristics(accounts.bob, accounts.alice, 1);
+    }
+
     /// Simple scenario that `alice` register as a provider and `bob` tries to subscribe to her second plan
     /// `alice` has two plans. One is daily and other is monthly.
     /// `alice` also pays 100 because of the policy of the registering in contract.
@@ -507,6 +645,7 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
     }
 
@@ -543,6 +682,7 @@ pub mod tests {
             [0; 32],
             "alice".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
     }
 
@@ -581,6 +721,7 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -593,9 +734,9 @@ pub mod tests {
             &accounts.alice
         );
         set_caller(callee, accounts.alice, 0);
-        let expected = subscrypt.get_withdrawable_amount();
+        let expected = subscrypt.get_withdrawable_amount(100);
         assert_eq!(
-            subscrypt.withdraw(),
+            subscrypt.withdraw(100),
             expected
         );
     }
@@ -635,6 +776,7 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -648,7 +790,7 @@ pub mod tests {
         );
 
         set_caller(callee, accounts.eve, 0);
-        subscrypt.withdraw();
+        subscrypt.withdraw(100);
     }
 
     /// Simple scenario that `alice` register as a provider and `bob` will subscribe to her second plan
@@ -687,6 +829,7 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["value".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -700,7 +843,7 @@ pub mod tests {
             false
         );
         set_caller(callee, accounts.bob, 50000);
-        subscrypt.renew(accounts.alice, 1, vec!["value".to_string()]);
+        subscrypt.renew(accounts.alice, 1, vec!["value".to_string()], 0);
         assert_eq!(
             subscrypt
                 .records
@@ -766,6 +909,7 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -779,7 +923,7 @@ pub mod tests {
             false
         );
 
-        subscrypt.refund(accounts.alice, 1);
+        assert_eq!(subscrypt.refund(accounts.alice, 1), 5000);
         assert_eq!(
             subscrypt
                 .records
@@ -842,6 +986,7 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -858,13 +1003,15 @@ pub mod tests {
         subscrypt.refund(accounts.alice, 1);
         subscrypt.refund(accounts.alice, 1);
     }
+
     /// Simple scenario that `alice` register as a provider and `bob` will subscribe to her second plan
-    /// and then call `check_subscription` function and will get true
+    /// and then `alice` cancels `bob` subscription and refunds 7000 to him, 5000 from the locked money
+    /// and 2000 that `alice` sends alongside the call.
     /// `alice` has two plans. One is daily and other is monthly.
     /// `alice` also pays 100 because of the policy of the registering in contract.
     /// `bob` pays 50000 for her second plan price
     #[ink::test]
-    fn check_subscription_works() {
+    fn provider_cancel_subscription_works() {
         let mut subscrypt = Subscrypt::new();
 
         let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
@@ -872,7 +1019,8 @@ pub mod tests {
         let callee =
             ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
                 .expect("Cannot get contract id");
-
+        set_account_balance(accounts.alice, 100);
+        set_account_balance(accounts.bob, 50000);
         set_account_balance(callee, 50100);
         set_caller(callee, accounts.alice, 100);
         subscrypt_provider_register_routine(
@@ -886,13 +1034,20 @@ pub mod tests {
         );
 
         set_caller(callee, accounts.bob, 50000);
-
         subscrypt.subscribe(
             accounts.alice,
             1,
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
+        );
+
+        set_account_balance(callee, 7100);
+        set_caller(callee, accounts.alice, 2000);
+        assert_eq!(
+            subscrypt.provider_cancel_subscription(accounts.bob, 1, 7000),
+            7000
         );
         assert_eq!(
             subscrypt
@@ -903,42 +1058,33 @@ pub mod tests {
                 .get(0)
                 .unwrap()
                 .refunded,
-            false
-        );
-        assert_eq!(
-            subscrypt.check_subscription(accounts.bob, accounts.alice, 1),
             true
         );
+        assert_eq!(subscrypt.check_subscription(accounts.bob, accounts.alice, 1), false);
         assert_eq!(
-            subscrypt.check_subscription(accounts.bob, accounts.alice, 0),
-            false
-        );
-
-        assert_eq!(
-            subscrypt.check_subscription_with_username("bob".to_string(), accounts.alice, 1),
-            true
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.bob)
+                .expect("Cannot set account balance"),
+            57000
         );
         assert_eq!(
-            subscrypt.check_subscription_with_username("bob".to_string(), accounts.alice, 0),
-            false
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(callee)
+                .expect("Cannot set account balance"),
+            100
         );
     }
 
-    /// Simple scenario that `alice` register as a provider and `bob` will subscribe to her second plan
-    /// and put it's token and pass phrase this: token, pass_phrase.
-    /// `bob` now tries to retrieve his data with that combination and he will successfully
-    /// get the data.
-    /// `alice` has two plans. One is daily and other is monthly.
-    /// `alice` also pays 100 because of the policy of the registering in contract.
-    /// `bob` pays 50000 for her second plan price
+    /// Simple scenario that `alice` tries to cancel `bob` subscription and refund him more than
+    /// the locked money without sending the extra amount, so it will fail
     #[ink::test]
-    fn retrieve_data_with_username_works() {
+    #[should_panic(expected = "Refund amount is more than the locked and paid money")]
+    fn provider_cancel_subscription_fails_insufficient_money() {
         let mut subscrypt = Subscrypt::new();
         let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
             .expect("Cannot get accounts");
         let callee =
             ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
                 .expect("Cannot get contract id");
+
         set_account_balance(callee, 50100);
         set_caller(callee, accounts.alice, 100);
         subscrypt_provider_register_routine(
@@ -950,50 +1096,38 @@ pub mod tests {
             "alice".to_string(),
             vec![vec!["key".to_string()], vec!["key".to_string()]],
         );
-
         set_caller(callee, accounts.bob, 50000);
-        let p: String = "pass_phrase".to_string();
-        let encodable = [p];
-        let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
-        ink_env::hash_encoded::<Sha2x256, _>(&encodable, &mut output);
-
         subscrypt.subscribe(
             accounts.alice,
             1,
-            output,
+            [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
-        assert_eq!(
-            subscrypt
-                .records
-                .get(&(accounts.bob, accounts.alice))
-                .unwrap()
-                .subscription_records
-                .get(0)
-                .unwrap()
-                .refunded,
-            false
-        );
-        let s = subscrypt.retrieve_data_with_username(
-            "bob".to_string(),
-            accounts.alice,
-            "pass_phrase".parse().unwrap(),
-        );
-        assert_eq!(s[0].provider, accounts.alice);
-        assert_eq!(s[0].plan_index, 1);
-        assert_eq!(s[0].plan.duration, 60 * 60 * 24 * 30);
+
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.provider_cancel_subscription(accounts.bob, 1, 5001);
     }
-    /// Check comments of `retrieve_data_with_password_works` function
+
+    /// Simple scenario that `alice` register as a provider and splits her earned money between
+    /// `charlie` and `django` and sets a separate payout table for her second plan which pays
+    /// everything to `eve`. Then `bob` subscribes to both plans.
+    /// `alice` has two plans. One is daily and other is monthly.
+    /// `bob` pays 10000 for the first plan that 9500 of it is split and the rounding dust goes to `charlie`
+    /// `bob` pays 50000 for the second plan that 45000 of it goes to `eve`
     #[ink::test]
-    fn retrieve_data_with_wallet_works() {
+    fn payout_table_works() {
         let mut subscrypt = Subscrypt::new();
         let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
             .expect("Cannot get accounts");
         let callee =
             ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
                 .expect("Cannot get contract id");
-        set_account_balance(callee, 50100);
+        set_account_balance(accounts.charlie, 0);
+        set_account_balance(accounts.django, 0);
+        set_account_balance(accounts.eve, 0);
+        set_account_balance(callee, 60100);
         set_caller(callee, accounts.alice, 100);
         subscrypt_provider_register_routine(
             &mut subscrypt,
@@ -1004,88 +1138,125 @@ pub mod tests {
             "alice".to_string(),
             vec![vec!["key".to_string()], vec!["key".to_string()]],
         );
+        subscrypt.set_payout_table(vec![accounts.charlie, accounts.django], vec![333, 667]);
+        subscrypt.set_plan_payout_table(1, vec![accounts.eve], vec![1000]);
+        assert_eq!(
+            subscrypt.get_payout_table(accounts.alice, 1),
+            vec![PayoutShare {
+                account: accounts.eve,
+                share_permille: 1000
+            }]
+        );
 
+        set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
         set_caller(callee, accounts.bob, 50000);
-
         subscrypt.subscribe(
             accounts.alice,
             1,
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
-            subscrypt
-                .records
-                .get(&(accounts.bob, accounts.alice))
-                .unwrap()
-                .subscription_records
-                .get(0)
-                .unwrap()
-                .refunded,
-            false
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.charlie)
+                .expect("Cannot set account balance"),
+            3164
+        );
+        assert_eq!(
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.django)
+                .expect("Cannot set account balance"),
+            6336
+        );
+        assert_eq!(
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.eve)
+                .expect("Cannot set account balance"),
+            45000
         );
-        let s = subscrypt.retrieve_data_with_wallet(accounts.alice);
-        assert_eq!(s[0].provider, accounts.alice);
-        assert_eq!(s[0].plan_index, 1);
-        assert_eq!(s[0].plan.duration, 60 * 60 * 24 * 30);
     }
-    /// Check comments of `retrieve_data_with_password_works` function
+
+    /// Simple scenario that `alice` pays her first plan to `charlie` and her second plan to `eve`
+    /// and `bob` subscribes to both plans. After a day `alice` withdraws the locked money and the
+    /// locked money of each plan goes to the payees of that plan.
+    /// `bob` pays 10000 for the first plan that 500 of it is locked
+    /// `bob` pays 50000 for the second plan that 5000 of it is locked
     #[ink::test]
-    fn retrieve_whole_data_with_wallet_works() {
+    fn withdraw_works_with_plan_payout_table() {
         let mut subscrypt = Subscrypt::new();
         let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
             .expect("Cannot get accounts");
         let callee =
             ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
                 .expect("Cannot get contract id");
-        set_account_balance(callee, 50100);
+        set_account_balance(accounts.charlie, 0);
+        set_account_balance(accounts.eve, 0);
+        set_account_balance(callee, 60100);
         set_caller(callee, accounts.alice, 100);
         subscrypt_provider_register_routine(
             &mut subscrypt,
             accounts.alice,
-            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![0, 0],
             vec![10000, 50000],
             vec![50, 100],
             "alice".to_string(),
             vec![vec!["key".to_string()], vec!["key".to_string()]],
         );
+        subscrypt.set_payout_table(vec![accounts.charlie], vec![1000]);
+        subscrypt.set_plan_payout_table(1, vec![accounts.eve], vec![1000]);
 
+        set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
         set_caller(callee, accounts.bob, 50000);
-
         subscrypt.subscribe(
             accounts.alice,
             1,
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
+
+        advance_time(60 * 60 * 24);
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(subscrypt.get_withdrawable_amount(100), 5500);
+        assert_eq!(subscrypt.withdraw(100), 5500);
         assert_eq!(
-            subscrypt
-                .records
-                .get(&(accounts.bob, accounts.alice))
-                .unwrap()
-                .subscription_records
-                .get(0)
-                .unwrap()
-                .refunded,
-            false
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.charlie)
+                .expect("Cannot set account balance"),
+            10000
+        );
+        assert_eq!(
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.eve)
+                .expect("Cannot set account balance"),
+            50000
         );
-        let s = subscrypt.retrieve_whole_data_with_wallet();
-        assert_eq!(s[0].provider, accounts.alice);
-        assert_eq!(s[0].plan_index, 1);
-        assert_eq!(s[0].plan.duration, 60 * 60 * 24 * 30);
     }
-    /// Check comments of `retrieve_data_with_password_works` function
+
+    /// Simple scenario that `alice` tries to set a payout table which its shares don't sum to 1000
     #[ink::test]
-    fn retrieve_whole_data_with_username_works() {
+    #[should_panic(expected = "Sum of shares must be 1000")]
+    fn payout_table_fails_wrong_shares() {
         let mut subscrypt = Subscrypt::new();
         let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
             .expect("Cannot get accounts");
         let callee =
             ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
                 .expect("Cannot get contract id");
-        set_account_balance(callee, 50100);
         set_caller(callee, accounts.alice, 100);
         subscrypt_provider_register_routine(
             &mut subscrypt,
@@ -1096,55 +1267,120 @@ pub mod tests {
             "alice".to_string(),
             vec![vec!["key".to_string()], vec!["key".to_string()]],
         );
+        subscrypt.set_payout_table(vec![accounts.charlie, accounts.django], vec![500, 400]);
+    }
 
-        set_caller(callee, accounts.bob, 50000);
-
-        let p: String = "pass_phrase".to_string();
-        let encodable = [p];
-        let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
-        ink_env::hash_encoded::<Sha2x256, _>(&encodable, &mut output);
-
-        subscrypt.subscribe(
-            accounts.alice,
-            1,
-            output,
-            "bob".to_string(),
-            vec!["nothing important".to_string()],
-        );
-        assert_eq!(
-            subscrypt
-                .records
-                .get(&(accounts.bob, accounts.alice))
-                .unwrap()
-                .subscription_records
-                .get(0)
-                .unwrap()
-                .refunded,
-            false
+    /// Simple scenario that `alice` register as a provider and `bob` will subscribe to both of her plans
+    /// and then `bob` refunds his second plan. `charlie` who is an operator of `alice` checks the
+    /// escrow schedule and statement of `alice`.
+    /// `alice` has two plans. One is daily and other is monthly.
+    /// `bob` pays 10000 and 50000 for the plans, so 500 is locked for day 1 and 5000 for day 30
+    #[ink::test]
+    fn escrow_statement_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 60100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.add_operator(accounts.charlie);
+
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        set_caller(callee, accounts.charlie, 0);
+        assert_eq!(
+            subscrypt.get_escrow_schedule(accounts.alice, 0, 100),
+            vec![(1, 500), (30, 5000)]
+        );
+
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.refund(accounts.alice, 1);
+
+        set_caller(callee, accounts.charlie, 0);
+        assert_eq!(
+            subscrypt.get_escrow_schedule(accounts.alice, 0, 100),
+            vec![(1, 500)]
+        );
+        assert_eq!(
+            subscrypt.get_escrow_statement(accounts.alice),
+            EscrowStatement {
+                total_locked: 500,
+                total_withdrawn: 0,
+                total_refunded: 5000
+            }
         );
-        let s = subscrypt
-            .retrieve_whole_data_with_username("bob".to_string(), "pass_phrase".parse().unwrap());
-        assert_eq!(s[0].provider, accounts.alice);
-        assert_eq!(s[0].plan_index, 1);
-        assert_eq!(s[0].plan.duration, 60 * 60 * 24 * 30);
     }
 
+    /// Simple scenario that `eve` who is not an operator of `alice` tries to see the escrow
+    /// statement of `alice`, so it will fail
+    #[ink::test]
+    #[should_panic(expected = "You are not the provider or its operator")]
+    fn escrow_statement_fails_not_operator() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.add_operator(accounts.charlie);
+        subscrypt.remove_operator(accounts.charlie);
+
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.get_escrow_statement(accounts.alice);
+    }
     /// Simple scenario that `alice` register as a provider and `bob` will subscribe to her second plan
-    /// and put it's token and pass phrase this: token, pass_phrase.
-    /// `bob` now tries to check his authentication data and he will try two time with two pair of token
-    /// and pass phrase and he will fail in first try and authenticate in second try
-    /// get the data.
+    /// and then call `check_subscription` function and will get true
     /// `alice` has two plans. One is daily and other is monthly.
     /// `alice` also pays 100 because of the policy of the registering in contract.
     /// `bob` pays 50000 for her second plan price
     #[ink::test]
-    fn check_auth_works() {
+    fn check_subscription_works() {
         let mut subscrypt = Subscrypt::new();
+
         let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
             .expect("Cannot get accounts");
         let callee =
             ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
                 .expect("Cannot get contract id");
+
         set_account_balance(callee, 50100);
         set_caller(callee, accounts.alice, 100);
         subscrypt_provider_register_routine(
@@ -1159,17 +1395,13 @@ pub mod tests {
 
         set_caller(callee, accounts.bob, 50000);
 
-        let p: String = "pass_phrase".to_string();
-        let encodable = [p];
-        let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
-        ink_env::hash_encoded::<Sha2x256, _>(&encodable, &mut output);
-
         subscrypt.subscribe(
             accounts.alice,
             1,
-            output,
+            [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
             subscrypt
@@ -1183,129 +1415,106 @@ pub mod tests {
             false
         );
         assert_eq!(
-            subscrypt.check_auth(accounts.bob, accounts.alice, "pass_phras".parse().unwrap()),
-            false
-        );
-
-        // No record for user charlie & provider alice
-        assert_eq!(
-            subscrypt.check_auth(
-                accounts.charlie,
-                accounts.alice,
-                "pass_phrase".parse().unwrap()
-            ),
-            false
+            subscrypt.check_subscription(accounts.bob, accounts.alice, 1),
+            true
         );
-
         assert_eq!(
-            subscrypt.check_auth_with_username(
-                "bob".to_string(),
-                accounts.alice,
-                "pass_phras".to_string()
-            ),
+            subscrypt.check_subscription(accounts.bob, accounts.alice, 0),
             false
         );
 
         assert_eq!(
-            subscrypt.check_auth_with_username(
-                "bob".to_string(),
-                accounts.alice,
-                "pass_phrase".to_string(),
-            ),
-            true
-        );
-
-        assert_eq!(
-            subscrypt.check_auth(accounts.bob, accounts.alice, "pass_phrase".to_string()),
+            subscrypt.check_subscription_with_username("bob".to_string(), accounts.alice, 1),
             true
         );
-
         assert_eq!(
-            subscrypt
-                .provider_check_auth_with_username("alice".to_string(), "pass_phras".to_string()),
+            subscrypt.check_subscription_with_username("bob".to_string(), accounts.alice, 0),
             false
         );
-        assert_eq!(
-            subscrypt
-                .provider_check_auth_with_username("alice".to_string(), "pass_phrase".to_string()),
-            true
-        );
+    }
 
-        assert_eq!(
-            subscrypt.provider_check_auth(accounts.alice, "pass_phras".to_string()),
-            false
-        );
-        assert_eq!(
-            subscrypt.provider_check_auth(accounts.alice, "pass_phrase".to_string()),
-            true
+    /// Simple scenario that `alice` register as a provider and `bob` will subscribe to her second plan
+    /// and put it's token and pass phrase this: token, pass_phrase.
+    /// `bob` now tries to retrieve his data with that combination and he will successfully
+    /// get the data.
+    /// `alice` has two plans. One is daily and other is monthly.
+    /// `alice` also pays 100 because of the policy of the registering in contract.
+    /// `bob` pays 50000 for her second plan price
+    #[ink::test]
+    fn retrieve_data_with_username_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
         );
 
-        assert_eq!(
-            subscrypt.user_check_auth_with_username("bob".to_string(), "pass_phras".to_string()),
-            false
-        );
-        assert_eq!(
-            subscrypt.user_check_auth_with_username("bob".to_string(), "pass_phrase".to_string()),
-            true
-        );
+        set_caller(callee, accounts.bob, 50000);
+        let p: String = "pass_phrase".to_string();
+        let encodable = [p];
+        let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
+        ink_env::hash_encoded::<Sha2x256, _>(&encodable, &mut output);
 
-        assert_eq!(
-            subscrypt.user_check_auth(accounts.bob, "pass_phras".to_string()),
-            false
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            output,
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
         );
         assert_eq!(
-            subscrypt.user_check_auth(accounts.bob, "pass_phrase".to_string()),
-            true
+            subscrypt
+                .records
+                .get(&(accounts.bob, accounts.alice))
+                .unwrap()
+                .subscription_records
+                .get(0)
+                .unwrap()
+                .refunded,
+            false
         );
-
-        assert_eq!(subscrypt.is_username_available("bobb".to_string()), true);
-        assert_eq!(subscrypt.is_username_available("bob".to_string()), false);
-        set_caller(callee, accounts.bob, 0);
-        assert_eq!(subscrypt.get_username(), "bob".to_string());
-        set_caller(callee, accounts.alice, 0);
-        assert_eq!(subscrypt.get_username(), "alice".to_string());
-        assert_eq!(
-            subscrypt.get_plan_data(accounts.alice, 0),
-            PlanConsts {
-                duration: 60 * 60 * 24,
-                price: 10000,
-                max_refund_permille_policy: 50,
-                disabled: false
-            }
+        let s = subscrypt.retrieve_data_with_username(
+            "bob".to_string(),
+            accounts.alice,
+            "pass_phrase".parse().unwrap(),
         );
+        assert_eq!(s[0].provider, accounts.alice);
+        assert_eq!(s[0].plan_index, 1);
+        assert_eq!(s[0].plan.duration, 60 * 60 * 24 * 30);
     }
-
+    /// Check comments of `retrieve_data_with_password_works` function
     #[ink::test]
-    fn add_entry_works() {
+    fn retrieve_data_with_wallet_works() {
         let mut subscrypt = Subscrypt::new();
-
         let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
             .expect("Cannot get accounts");
-
         let callee =
             ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
                 .expect("Cannot get contract id");
-        set_account_balance(callee, 90100);
+        set_account_balance(callee, 50100);
         set_caller(callee, accounts.alice, 100);
         subscrypt_provider_register_routine(
             &mut subscrypt,
             accounts.alice,
-            vec![
-                60 * 60 * 24,
-                60 * 60 * 24 * 30,
-                60 * 60 * 24 * 300,
-                60 * 60 * 24 * 31,
-            ],
-            vec![10000, 50000, 10000, 10000],
-            vec![50, 100, 200, 100],
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
             "alice".to_string(),
-            vec![
-                vec!["key".to_string()],
-                vec!["key".to_string()],
-                vec!["key".to_string()],
-                vec!["key".to_string()],
-            ],
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
         );
+
         set_caller(callee, accounts.bob, 50000);
 
         subscrypt.subscribe(
@@ -1314,52 +1523,3009 @@ pub mod tests {
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
+        );
+        assert_eq!(
+            subscrypt
+                .records
+                .get(&(accounts.bob, accounts.alice))
+                .unwrap()
+                .subscription_records
+                .get(0)
+                .unwrap()
+                .refunded,
+            false
+        );
+        let s = subscrypt.retrieve_data_with_wallet(accounts.alice);
+        assert_eq!(s[0].provider, accounts.alice);
+        assert_eq!(s[0].plan_index, 1);
+        assert_eq!(s[0].plan.duration, 60 * 60 * 24 * 30);
+    }
+    /// Check comments of `retrieve_data_with_password_works` function
+    #[ink::test]
+    fn retrieve_whole_data_with_wallet_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+
+        set_caller(callee, accounts.bob, 50000);
+
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        assert_eq!(
+            subscrypt
+                .records
+                .get(&(accounts.bob, accounts.alice))
+                .unwrap()
+                .subscription_records
+                .get(0)
+                .unwrap()
+                .refunded,
+            false
+        );
+        let s = subscrypt.retrieve_whole_data_with_wallet();
+        assert_eq!(s[0].provider, accounts.alice);
+        assert_eq!(s[0].plan_index, 1);
+        assert_eq!(s[0].plan.duration, 60 * 60 * 24 * 30);
+    }
+    /// Check comments of `retrieve_data_with_password_works` function
+    #[ink::test]
+    fn retrieve_whole_data_with_username_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+
+        set_caller(callee, accounts.bob, 50000);
+
+        let p: String = "pass_phrase".to_string();
+        let encodable = [p];
+        let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
+        ink_env::hash_encoded::<Sha2x256, _>(&encodable, &mut output);
+
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            output,
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        assert_eq!(
+            subscrypt
+                .records
+                .get(&(accounts.bob, accounts.alice))
+                .unwrap()
+                .subscription_records
+                .get(0)
+                .unwrap()
+                .refunded,
+            false
+        );
+        let s = subscrypt
+            .retrieve_whole_data_with_username("bob".to_string(), "pass_phrase".parse().unwrap());
+        assert_eq!(s[0].provider, accounts.alice);
+        assert_eq!(s[0].plan_index, 1);
+        assert_eq!(s[0].plan.duration, 60 * 60 * 24 * 30);
+    }
+
+    /// Simple scenario that `alice` register as a provider and `bob` will subscribe to her second plan
+    /// and put it's token and pass phrase this: token, pass_phrase.
+    /// `bob` now tries to check his authentication data and he will try two time with two pair of token
+    /// and pass phrase and he will fail in first try and authenticate in second try
+    /// get the data.
+    /// `alice` has two plans. One is daily and other is monthly.
+    /// `alice` also pays 100 because of the policy of the registering in contract.
+    /// `bob` pays 50000 for her second plan price
+    #[ink::test]
+    fn check_auth_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+
+        set_caller(callee, accounts.bob, 50000);
+
+        let p: String = "pass_phrase".to_string();
+        let encodable = [p];
+        let mut output = <Sha2x256 as HashOutput>::Type::default(); // 256-bit buffer
+        ink_env::hash_encoded::<Sha2x256, _>(&encodable, &mut output);
+
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            output,
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        assert_eq!(
+            subscrypt
+                .records
+                .get(&(accounts.bob, accounts.alice))
+                .unwrap()
+                .subscription_records
+                .get(0)
+                .unwrap()
+                .refunded,
+            false
+        );
+        assert_eq!(
+            subscrypt.check_auth(accounts.bob, accounts.alice, "pass_phras".parse().unwrap()),
+            false
+        );
+
+        // No record for user charlie & provider alice
+        assert_eq!(
+            subscrypt.check_auth(
+                accounts.charlie,
+                accounts.alice,
+                "pass_phrase".parse().unwrap()
+            ),
+            false
+        );
+
+        assert_eq!(
+            subscrypt.check_auth_with_username(
+                "bob".to_string(),
+                accounts.alice,
+                "pass_phras".to_string()
+            ),
+            false
+        );
+
+        assert_eq!(
+            subscrypt.check_auth_with_username(
+                "bob".to_string(),
+                accounts.alice,
+                "pass_phrase".to_string(),
+            ),
+            true
+        );
+
+        assert_eq!(
+            subscrypt.check_auth(accounts.bob, accounts.alice, "pass_phrase".to_string()),
+            true
+        );
+
+        assert_eq!(
+            subscrypt
+                .provider_check_auth_with_username("alice".to_string(), "pass_phras".to_string()),
+            false
+        );
+        assert_eq!(
+            subscrypt
+                .provider_check_auth_with_username("alice".to_string(), "pass_phrase".to_string()),
+            true
+        );
+
+        assert_eq!(
+            subscrypt.provider_check_auth(accounts.alice, "pass_phras".to_string()),
+            false
+        );
+        assert_eq!(
+            subscrypt.provider_check_auth(accounts.alice, "pass_phrase".to_string()),
+            true
+        );
+
+        assert_eq!(
+            subscrypt.user_check_auth_with_username("bob".to_string(), "pass_phras".to_string()),
+            false
+        );
+        assert_eq!(
+            subscrypt.user_check_auth_with_username("bob".to_string(), "pass_phrase".to_string()),
+            true
+        );
+
+        assert_eq!(
+            subscrypt.user_check_auth(accounts.bob, "pass_phras".to_string()),
+            false
+        );
+        assert_eq!(
+            subscrypt.user_check_auth(accounts.bob, "pass_phrase".to_string()),
+            true
+        );
+
+        assert_eq!(subscrypt.is_username_available("bobb".to_string()), true);
+        assert_eq!(subscrypt.is_username_available("bob".to_string()), false);
+        set_caller(callee, accounts.bob, 0);
+        assert_eq!(subscrypt.get_username(), "bob".to_string());
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(subscrypt.get_username(), "alice".to_string());
+        assert_eq!(
+            subscrypt.get_plan_data(accounts.alice, 0),
+            PlanConsts {
+                duration: 60 * 60 * 24,
+                price: 10000,
+                max_refund_permille_policy: 50,
+                disabled: false
+            }
+        );
+    }
+
+    #[ink::test]
+    fn add_entry_works() {
+        let mut subscrypt = Subscrypt::new();
+
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 90100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![
+                60 * 60 * 24,
+                60 * 60 * 24 * 30,
+                60 * 60 * 24 * 300,
+                60 * 60 * 24 * 31,
+            ],
+            vec![10000, 50000, 10000, 10000],
+            vec![50, 100, 200, 100],
+            "alice".to_string(),
+            vec![
+                vec!["key".to_string()],
+                vec!["key".to_string()],
+                vec!["key".to_string()],
+                vec!["key".to_string()],
+            ],
+        );
+        set_caller(callee, accounts.bob, 50000);
+
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller(callee, accounts.bob, 10000);
+
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        subscrypt.subscribe(
+            accounts.alice,
+            2,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller(callee, accounts.eve, 10000);
+
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "eve".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        subscrypt.subscribe(
+            accounts.alice,
+            3,
+            [0; 32],
+            "eve".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        assert_eq!(
+            subscrypt
+                .users
+                .get(&accounts.bob)
+                .unwrap()
+                .list_of_providers
+                .get(0)
+                .unwrap(),
+            &accounts.alice
+        );
+        set_caller(callee, accounts.alice, 0);
+
+        let schedule: Vec<(u64, u128)> = subscrypt.get_escrow_schedule(accounts.alice, 0, 1000);
+        assert_eq!(schedule.len(), 4);
+        assert_eq!(
+            schedule.iter().map(|(_, amount)| amount).sum::<u128>(),
+            5000 + 500 + 2000 + 500 + 1000
+        );
+    }
+
+    /// Scenario that `bob` subscribes to plans of `alice` which end in days 0, 1, 2 and 300.
+    /// After 301 days `alice` withdraws the locked money in small pages. The second word of the
+    /// escrow bitmap is empty and skipping it uses the whole page of a call, then the far entry of
+    /// day 300 is withdrawn by the next call, so the cost of each call stays bounded by its page.
+    #[ink::test]
+    fn escrow_bounded_cost_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 4100);
+        set_caller(callee, accounts.alice, 100);
+        let day: u64 = 60 * 60 * 24;
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![0, day, 2 * day, 300 * day],
+            vec![1000, 1000, 1000, 1000],
+            vec![100, 100, 100, 100],
+            "alice".to_string(),
+            vec![vec![], vec![], vec![], vec![]],
+        );
+        set_caller(callee, accounts.bob, 1000);
+        for plan_index in 0..4 {
+            subscrypt.subscribe(
+                accounts.alice,
+                plan_index,
+                [0; 32],
+                "bob".to_string(),
+                vec![],
+                0,
+            );
+        }
+
+        advance_time(301 * day);
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(subscrypt.get_withdrawable_amount(2), 200);
+        assert_eq!(subscrypt.withdraw(2), 200);
+        assert_eq!(subscrypt.withdraw(1), 100);
+        assert_eq!(subscrypt.withdraw(1), 0);
+        assert_eq!(
+            subscrypt.get_escrow_schedule(accounts.alice, 0, 400),
+            vec![(300, 100)]
+        );
+        assert_eq!(subscrypt.withdraw(1), 100);
+        assert_eq!(subscrypt.withdraw(1), 0);
+    }
+
+    /// Benchmark scenario that `alice` has locked money in 10000 distinct days which have a gap of
+    /// 1000 empty days after each 1000 days. Adding each entry must touch one bucket and at most
+    /// one word of the bitmap, and each page of withdrawing must touch at most `max_buckets`
+    /// buckets and `max_buckets + 1` words no matter how many buckets are left.
+    #[ink::test]
+    fn escrow_bounded_cost_with_10000_days_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![1000],
+            vec![100],
+            "alice".to_string(),
+            vec![vec![]],
+        );
+        let buckets_per_word: u64 = 128;
+        let mut total: u128 = 0;
+        for i in 0..10000u64 {
+            let buckets = subscrypt.daily_locked_amounts.len();
+            let words = subscrypt.escrow_bitmaps.len();
+            subscrypt.add_entry(accounts.alice, 0, i + (i / 1000) * 1000, u128::from(i) + 1);
+            total += u128::from(i) + 1;
+            assert_eq!(subscrypt.daily_locked_amounts.len(), buckets + 1);
+            assert!(subscrypt.escrow_bitmaps.len() <= words + 1);
+        }
+        let escrow_index = &subscrypt.providers.get(&accounts.alice).unwrap().payment_manager;
+        assert_eq!(escrow_index.length, 10000);
+        assert_eq!(escrow_index.total_locked, total);
+
+        let max_buckets: u128 = 50;
+        let mut withdrawn: u128 = 0;
+        let mut pages: u128 = 0;
+        loop {
+            let cursor: u64 = subscrypt
+                .providers
+                .get(&accounts.alice)
+                .unwrap()
+                .payment_manager
+                .cursor;
+            let t = subscrypt.release_escrow(accounts.alice, 20000, max_buckets);
+            if t.withdrawing_amount == 0 {
+                break;
+            }
+            let visited_words: u64 =
+                (t.next_cursor - 1) / buckets_per_word - cursor / buckets_per_word + 1;
+            assert!(t.processed_days.len() as u128 <= max_buckets);
+            assert!(u128::from(visited_words) <= max_buckets + 1);
+            withdrawn += t.withdrawing_amount;
+            pages += 1;
+        }
+        assert_eq!(withdrawn, total);
+        assert!(pages >= 10000 / max_buckets);
+        let escrow_index = &subscrypt.providers.get(&accounts.alice).unwrap().payment_manager;
+        assert_eq!(escrow_index.length, 0);
+        assert_eq!(escrow_index.total_locked, 0);
+        assert_eq!(escrow_index.total_withdrawn, total);
+        assert_eq!(subscrypt.daily_locked_amounts.len(), 0);
+        assert_eq!(subscrypt.escrow_bitmaps.len(), 0);
+    }
+
+    /// Randomized scenario that `bob`, `charlie`, `django`, `eve` and `frank` subscribe to plans
+    /// of `alice` with odd prices and refund policies, then renew, refund or get cancelled by
+    /// `alice` while `alice` withdraws and time passes. The contract must stay solvent after
+    /// every step.
+    #[ink::test]
+    fn audit_solvency_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 0);
+        set_caller_and_pay(callee, accounts.alice, 100);
+        let durations: Vec<u64> = vec![60 * 60 * 24 * 7, 60 * 60 * 24 * 30, 60 * 60 * 24];
+        let prices: Vec<u128> = vec![10007, 49999, 333];
+        let policies: Vec<u128> = vec![333, 77, 999];
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            durations.clone(),
+            prices.clone(),
+            policies.clone(),
+            "alice".to_string(),
+            vec![vec![], vec![], vec![]],
+        );
+        assert!(subscrypt.audit_solvency());
+
+        let users = vec![
+            accounts.bob,
+            accounts.charlie,
+            accounts.django,
+            accounts.eve,
+            accounts.frank,
+        ];
+        let names = vec!["bob", "charlie", "django", "eve", "frank"];
+        // start time of the last subscription record of each user to each plan
+        let mut starts = vec![[0u64; 3]; 5];
+        let mut seed: u64 = 0x5eed;
+        for _ in 0..300 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let user = ((seed >> 33) % 5) as usize;
+            let plan = ((seed >> 40) % 3) as usize;
+            let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+            let subscribed: bool =
+                subscrypt.check_subscription(users[user], accounts.alice, plan as u128);
+            match (seed >> 50) % 6 {
+                0 | 1 if !subscribed => {
+                    set_caller_and_pay(callee, users[user], prices[plan]);
+                    subscrypt.subscribe(
+                        accounts.alice,
+                        plan as u128,
+                        [0; 32],
+                        names[user].to_string(),
+                        vec![],
+                        0,
+                    );
+                    starts[user][plan] = now;
+                }
+                0 => {
+                    set_caller_and_pay(callee, users[user], prices[plan]);
+                    subscrypt.renew(accounts.alice, plan as u128, vec![], 0);
+                    starts[user][plan] += durations[plan];
+                }
+                1 if starts[user][plan] <= now => {
+                    set_caller(callee, users[user], 0);
+                    subscrypt.refund(accounts.alice, plan as u128);
+                }
+                2 if subscribed => {
+                    let locked = prices[plan] * policies[plan] / 1000;
+                    set_caller(callee, accounts.alice, 0);
+                    subscrypt.provider_cancel_subscription(
+                        users[user],
+                        plan as u128,
+                        (seed >> 20) as u128 % (locked + 1),
+                    );
+                }
+                3 => {
+                    set_caller(callee, accounts.alice, 0);
+                    subscrypt.withdraw((seed >> 20) as u128 % 4 + 1);
+                }
+                4 => advance_time((seed >> 12) % (60 * 60 * 12)),
+                _ => {}
+            }
+            assert!(subscrypt.audit_solvency());
+        }
+    }
+
+    /// Simple scenario that `alice` has a plan with a huge price and `bob` tries to subscribe,
+    /// so the escrow calculation overflows and it fails
+    #[ink::test]
+    #[should_panic(expected = "Arithmetic error in money calculation")]
+    fn subscribe_fails_overflow() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![u128::MAX],
+            vec![500],
+            "alice".to_string(),
+            vec![vec![]],
+        );
+
+        set_caller(callee, accounts.bob, u128::MAX);
+        subscrypt.subscribe(accounts.alice, 0, [0; 32], "bob".to_string(), vec![], 0);
+    }
+
+    /// Simple scenario that `bob` subscribes to `alice` with the unsalted `get_sha2` pass hash
+    /// that old clients send, which is stored like an upgraded legacy hash, and then `bob` and
+    /// `alice` set salted credentials with different algorithms
+    #[ink::test]
+    fn set_credential_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+
+        let pass: String = "pass_phrase".to_string();
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            subscrypt.get_sha2(pass.clone()),
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        let legacy_hash: [u8; 32] = subscrypt.get_sha2(pass.clone());
+        let credential: Credential = subscrypt
+            .users
+            .get(&accounts.bob)
+            .unwrap()
+            .subscrypt_credential
+            .clone();
+        assert_ne!(credential.hash, legacy_hash);
+        assert_eq!(
+            credential,
+            Credential::from_pass(legacy_hash, credential.salt.clone())
+        );
+        assert!(subscrypt.user_check_auth(accounts.bob, pass.clone()));
+
+        let new_pass: String = "new_pass_phrase".to_string();
+        let salt: Vec<u8> = vec![7, 1, 2, 9];
+        subscrypt.set_user_subscrypt_credential(
+            salt.clone(),
+            HashAlgorithm::Blake2x256,
+            10,
+            Credential::derive(&new_pass, &salt, HashAlgorithm::Blake2x256, 10),
+        );
+        assert!(!subscrypt.user_check_auth(accounts.bob, pass.clone()));
+        assert!(subscrypt.user_check_auth(accounts.bob, new_pass.clone()));
+        subscrypt.retrieve_whole_data_with_username("bob".to_string(), new_pass.clone());
+
+        subscrypt.set_credential_for_each_provider(
+            accounts.alice,
+            salt.clone(),
+            HashAlgorithm::Sha2x256,
+            3,
+            Credential::derive(&new_pass, &salt, HashAlgorithm::Sha2x256, 3),
+        );
+        assert!(!subscrypt.check_auth(accounts.bob, accounts.alice, pass.clone()));
+        assert!(subscrypt.check_auth(accounts.bob, accounts.alice, new_pass.clone()));
+
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.set_provider_subscrypt_credential(
+            vec![42],
+            HashAlgorithm::Keccak256,
+            1,
+            Credential::derive(&new_pass, &[42], HashAlgorithm::Keccak256, 1),
+        );
+        assert!(!subscrypt.provider_check_auth(accounts.alice, pass));
+        assert!(subscrypt.provider_check_auth(accounts.alice, new_pass));
+    }
+
+    /// Simple scenario that `bob` subscribes to `alice` and `charlie` and `eve` subscribes to
+    /// `alice` with the same pass_phrase, each credential gets its own random salt so the stored
+    /// hashes are different
+    #[ink::test]
+    fn pass_credential_random_salt_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 30200);
+        for (provider, name) in [(accounts.alice, "alice"), (accounts.charlie, "charlie")].iter() {
+            set_caller(callee, *provider, 100);
+            subscrypt_provider_register_routine(
+                &mut subscrypt,
+                *provider,
+                vec![60 * 60 * 24],
+                vec![10000],
+                vec![50],
+                name.to_string(),
+                vec![vec!["key".to_string()]],
+            );
+        }
+
+        let pass: String = "pass_phrase".to_string();
+        let pass_hash: [u8; 32] = subscrypt.get_sha2(pass.clone());
+        for (user, provider, name) in [
+            (accounts.bob, accounts.alice, "bob"),
+            (accounts.bob, accounts.charlie, "bob"),
+            (accounts.eve, accounts.alice, "eve"),
+        ]
+        .iter()
+        {
+            set_caller(callee, *user, 10000);
+            subscrypt.subscribe(
+                *provider,
+                0,
+                pass_hash,
+                name.to_string(),
+                vec!["nothing important".to_string()],
+                0,
+            );
+        }
+
+        let credentials: Vec<Credential> = vec![
+            subscrypt
+                .records
+                .get(&(accounts.bob, accounts.alice))
+                .unwrap()
+                .credential
+                .clone(),
+            subscrypt
+                .records
+                .get(&(accounts.bob, accounts.charlie))
+                .unwrap()
+                .credential
+                .clone(),
+            subscrypt
+                .records
+                .get(&(accounts.eve, accounts.alice))
+                .unwrap()
+                .credential
+                .clone(),
+            subscrypt
+                .users
+                .get(&accounts.bob)
+                .unwrap()
+                .subscrypt_credential
+                .clone(),
+            subscrypt
+                .users
+                .get(&accounts.eve)
+                .unwrap()
+                .subscrypt_credential
+                .clone(),
+        ];
+        for (i, credential) in credentials.iter().enumerate() {
+            assert!(credential.verify(&pass));
+            for other in credentials[i + 1..].iter() {
+                assert_ne!(credential.salt, other.salt);
+                assert_ne!(credential.hash, other.hash);
+            }
+        }
+        assert!(subscrypt.check_auth(accounts.bob, accounts.alice, pass.clone()));
+        assert!(subscrypt.check_auth(accounts.bob, accounts.charlie, pass.clone()));
+        assert!(subscrypt.check_auth(accounts.eve, accounts.alice, pass.clone()));
+        assert!(subscrypt.user_check_auth(accounts.bob, pass.clone()));
+        assert!(subscrypt.provider_check_auth(accounts.alice, pass.clone()));
+
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.set_user_subscrypt_pass(subscrypt.get_sha2("new_pass_phrase".to_string()));
+        assert!(!subscrypt.user_check_auth(accounts.bob, pass));
+        assert!(subscrypt.user_check_auth(accounts.bob, "new_pass_phrase".to_string()));
+    }
+
+    /// Simple scenario that `alice` tries to set a salted credential without any salt, so it
+    /// will fail
+    #[ink::test]
+    #[should_panic(expected = "Invalid salt length")]
+    fn set_credential_fails_empty_salt() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+        subscrypt.set_provider_subscrypt_credential(vec![], HashAlgorithm::Sha2x256, 1, [0; 32]);
+    }
+
+    /// Simple scenario that `bob` subscribes to `alice` and registers a login key, then a
+    /// dashboard verifies the signature of `bob` on its login challenge without any pass_phrase,
+    /// while forged signatures, signatures of other keys and replays on other challenges fail
+    #[ink::test]
+    fn verify_login_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        let login_key: Vec<u8> = vec![
+            2, 121, 109, 152, 165, 251, 233, 252, 242, 83, 62, 192, 142, 99, 71, 75, 94, 65, 223,
+            244, 25, 194, 23, 28, 233, 232, 168, 134, 198, 68, 88, 5, 14,
+        ];
+        let signature: Vec<u8> = vec![
+            71, 24, 10, 174, 113, 192, 247, 253, 251, 205, 191, 205, 70, 150, 204, 45, 247, 176,
+            36, 163, 179, 165, 170, 51, 178, 35, 146, 223, 75, 176, 203, 187, 9, 128, 180, 60, 234,
+            147, 193, 174, 10, 225, 243, 160, 120, 202, 146, 124, 158, 158, 250, 200, 89, 199, 161,
+            225, 128, 63, 42, 1, 185, 193, 29, 144, 0,
+        ];
+        let mut nonce = [0u8; 32];
+        for (i, byte) in nonce.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        assert_eq!(
+            subscrypt.get_login_message_hash(accounts.bob, nonce, 0),
+            [
+                84, 110, 24, 233, 255, 185, 131, 189, 11, 226, 206, 22, 20, 201, 32, 51, 141, 27,
+                230, 92, 36, 194, 77, 140, 104, 75, 99, 105, 22, 85, 32, 164
+            ]
+        );
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, 0, signature.clone()));
+
+        subscrypt.add_login_key(login_key);
+        assert!(subscrypt.verify_login(accounts.bob, nonce, 0, signature.clone()));
+        assert!(!subscrypt.verify_login(accounts.bob, [0; 32], 0, signature.clone()));
+        assert!(!subscrypt.verify_login(accounts.charlie, nonce, 0, signature.clone()));
+
+        let mut forged = signature.clone();
+        forged[40] ^= 1;
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, 0, forged));
+        let mut forged = signature.clone();
+        forged[64] = 1;
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, 0, forged));
+        let mut forged = signature.clone();
+        forged[64] = 2;
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, 0, forged));
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, 0, vec![0; 65]));
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, 0, signature[..64].to_vec()));
+
+        let other_key: [u8; 32] = [7; 32];
+        let message_hash = subscrypt.get_login_message_hash(accounts.bob, nonce, 0);
+        let other_signature = sign_login_message(other_key, message_hash);
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, 0, other_signature.clone()));
+        subscrypt.add_login_key(login_public_key(other_key));
+        assert!(subscrypt.verify_login(accounts.bob, nonce, 0, other_signature.clone()));
+
+        advance_time(60 * 5 + 5);
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, 0, signature));
+        let message_hash = subscrypt.get_login_message_hash(accounts.bob, nonce, now);
+        let fresh_signature = sign_login_message(other_key, message_hash);
+        assert!(subscrypt.verify_login(accounts.bob, nonce, now, fresh_signature.clone()));
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, now, other_signature));
+
+        subscrypt.remove_login_key(login_public_key(other_key));
+        assert!(!subscrypt.verify_login(accounts.bob, nonce, now, fresh_signature));
+    }
+
+    /// Simple scenario that `alice` tries to register an uncompressed public key as a login key,
+    /// so it will fail
+    #[ink::test]
+    #[should_panic(expected = "Invalid login key")]
+    fn add_login_key_fails_invalid_key() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.add_login_key(vec![4; 65]);
+    }
+
+    /// Simple scenario that `bob` subscribes to `alice` and registers an access token for a
+    /// session, then `alice` and the operator `charlie` verify the token until `bob` revokes it
+    #[ink::test]
+    fn access_token_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        let token: String = "bob session token".to_string();
+        let token_hash: [u8; 32] = subscrypt.get_sha2(token.clone());
+        let expires_at: u64 =
+            ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap() + 60 * 60;
+        subscrypt.register_access_token(token_hash, accounts.alice, 1, expires_at);
+        assert_eq!(
+            subscrypt.list_access_tokens(),
+            vec![(
+                token_hash,
+                AccessToken {
+                    user: accounts.bob,
+                    provider: accounts.alice,
+                    plan_index: 1,
+                    expires_at
+                }
+            )]
+        );
+
+        set_caller(callee, accounts.alice, 0);
+        assert!(subscrypt.verify_access_token(token.clone()));
+        assert!(!subscrypt.verify_access_token("wrong token".to_string()));
+        set_caller(callee, accounts.charlie, 0);
+        assert!(!subscrypt.verify_access_token(token.clone()));
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.add_operator(accounts.charlie);
+        set_caller(callee, accounts.charlie, 0);
+        assert!(subscrypt.verify_access_token(token.clone()));
+
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.revoke_access_token(token_hash);
+        assert_eq!(subscrypt.list_access_tokens().len(), 0);
+        set_caller(callee, accounts.alice, 0);
+        assert!(!subscrypt.verify_access_token(token));
+    }
+
+    /// Simple scenario that `charlie` who is not subscribed to `alice` tries to register an
+    /// access token for a plan of `alice`, so it will fail
+    #[ink::test]
+    #[should_panic(expected = "You are not subscribed to this plan")]
+    fn access_token_fails_not_subscribed() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.register_access_token([1; 32], accounts.alice, 0, 60);
+    }
+
+    /// Simple scenario that `bob` subscribes to `alice` and grants read access of the records
+    /// to `charlie` for `alice` and to `django` for all providers, then `bob` revokes the
+    /// grant of `charlie`
+    #[ink::test]
+    fn read_grant_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        let expires_at: u64 =
+            ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap() + 60 * 60;
+        subscrypt.grant_read_access(accounts.charlie, Some(accounts.alice), expires_at);
+        subscrypt.grant_read_access(accounts.django, None, expires_at);
+        subscrypt.grant_read_access(accounts.charlie, Some(accounts.alice), expires_at + 1);
+        assert_eq!(
+            subscrypt.get_read_grants(),
+            vec![
+                ReadGrant {
+                    grantee: accounts.charlie,
+                    provider: Some(accounts.alice),
+                    expires_at: expires_at + 1
+                },
+                ReadGrant {
+                    grantee: accounts.django,
+                    provider: None,
+                    expires_at
+                }
+            ]
+        );
+
+        set_caller(callee, accounts.charlie, 0);
+        let data = subscrypt.retrieve_data_for(accounts.bob, accounts.alice);
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].plan_index, 1);
+        set_caller(callee, accounts.django, 0);
+        assert_eq!(
+            subscrypt
+                .retrieve_data_for(accounts.bob, accounts.alice)
+                .len(),
+            1
+        );
+
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.revoke_read_access(accounts.charlie, Some(accounts.alice));
+        assert_eq!(subscrypt.get_read_grants().len(), 1);
+    }
+
+    /// Simple scenario that `bob` grants read access to `charlie` and then revokes it, so
+    /// `charlie` fails to retrieve the records of `bob`
+    #[ink::test]
+    #[should_panic(expected = "You don't have read access")]
+    fn read_grant_fails_revoked() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 10100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        let expires_at: u64 =
+            ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap() + 60 * 60;
+        subscrypt.grant_read_access(accounts.charlie, None, expires_at);
+        subscrypt.revoke_read_access(accounts.charlie, None);
+
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.retrieve_data_for(accounts.bob, accounts.alice);
+    }
+    /// Simple scenario that `alice` publishes an encryption key and `bob` subscribes with it,
+    /// then `alice` rotates the key and `bob` re-submits the values encrypted with the new key
+    #[ink::test]
+    fn encryption_key_rotation_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        assert_eq!(subscrypt.publish_encryption_key(vec![1, 2, 3]), 1);
+        assert_eq!(
+            subscrypt.get_encryption_key(accounts.alice),
+            (1, vec![1, 2, 3])
+        );
+
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["encrypted with first key".to_string()],
+            1,
+        );
+        assert_eq!(
+            subscrypt.retrieve_data_with_wallet(accounts.alice)[0].key_version,
+            1
+        );
+        set_caller_and_pay(callee, accounts.bob, 50000);
+        subscrypt.renew(
+            accounts.alice,
+            1,
+            vec!["encrypted with first key".to_string()],
+            1,
+        );
+
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(subscrypt.publish_encryption_key(vec![4, 5, 6]), 2);
+        assert_eq!(
+            subscrypt.get_encryption_key(accounts.alice),
+            (2, vec![4, 5, 6])
+        );
+        assert_eq!(
+            subscrypt.get_encryption_key_by_version(accounts.alice, 1),
+            vec![1, 2, 3]
+        );
+
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.resubmit_characteristics_values(
+            accounts.alice,
+            1,
+            vec!["encrypted with second key".to_string()],
+            2,
+        );
+        assert_eq!(
+            subscrypt.retrieve_data_with_wallet(accounts.alice)[0].key_version,
+            2
+        );
+        assert_eq!(
+            subscrypt.retrieve_data_with_wallet(accounts.alice)[1].key_version,
+            2
+        );
+        assert_eq!(
+            subscrypt.get_user_plan_characteristics(accounts.bob, accounts.alice, 1),
+            vec!["encrypted with second key".to_string()]
+        );
+    }
+
+    /// Simple scenario that `bob` tries to re-submit values encrypted with an old key of
+    /// `alice`, so it will fail
+    #[ink::test]
+    #[should_panic(expected = "Encryption key version is not current")]
+    fn resubmit_characteristics_values_fails_old_key() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 10100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+        subscrypt.publish_encryption_key(vec![1, 2, 3]);
+        subscrypt.publish_encryption_key(vec![4, 5, 6]);
+
+        set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            2,
+        );
+        subscrypt.resubmit_characteristics_values(
+            accounts.alice,
+            0,
+            vec!["nothing important".to_string()],
+            1,
+        );
+    }
+
+    /// Simple scenario that `bob` subscribes with values encrypted with an old key of `alice`,
+    /// so it will fail
+    #[ink::test]
+    #[should_panic(expected = "Encryption key version is not current")]
+    fn subscribe_fails_old_key() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 10100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+        subscrypt.publish_encryption_key(vec![1, 2, 3]);
+        subscrypt.publish_encryption_key(vec![4, 5, 6]);
+
+        set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            1,
+        );
+    }
+    /// Simple scenario that `bob` subscribes to `alice`, waits for a seat of two full plans of
+    /// `alice` and nominates `charlie`, `django` and `eve` as guardians with threshold 2, then
+    /// `charlie` and `django` move the identity, the seat offer and the waitlist entry of `bob`
+    /// to `frank`
+    #[ink::test]
+    fn recovery_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 50100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30, 60 * 60 * 24],
+            vec![10000, 50000, 10000],
+            vec![50, 100, 50],
+            "alice".to_string(),
+            vec![
+                vec!["key".to_string()],
+                vec!["key".to_string()],
+                vec!["key".to_string()],
+            ],
+        );
+        let capacity = PlanCapacity {
+            max_subscribers: 1,
+            waitlist_deposit: 100,
+            claim_window: 60 * 60 * 24 * 2,
+        };
+        subscrypt.set_plan_capacity(0, Some(capacity));
+        subscrypt.set_plan_capacity(2, Some(capacity));
+        for plan_index in [0, 2].iter() {
+            set_caller_and_pay(callee, accounts.eve, 10000);
+            subscrypt.subscribe(
+                accounts.alice,
+                *plan_index,
+                [0; 32],
+                "eve".to_string(),
+                vec!["nothing important".to_string()],
+                0,
+            );
+        }
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            subscrypt.get_sha2("pass_phrase".to_string()),
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller_and_pay(callee, accounts.bob, 100);
+        subscrypt.join_waitlist(accounts.alice, 0);
+        set_caller_and_pay(callee, accounts.bob, 100);
+        subscrypt.join_waitlist(accounts.alice, 2);
+        set_caller(callee, accounts.eve, 0);
+        subscrypt.refund(accounts.alice, 0);
+        let offered_at: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.set_recovery_config(
+            vec![accounts.charlie, accounts.django, accounts.eve],
+            2,
+            60 * 60 * 24,
+        );
+
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.approve_recovery(accounts.bob, accounts.frank);
+        assert_eq!(
+            subscrypt
+                .get_recovery_request(accounts.bob)
+                .unwrap()
+                .ready_at,
+            None
+        );
+        set_caller(callee, accounts.django, 0);
+        subscrypt.approve_recovery(accounts.bob, accounts.frank);
+        assert!(subscrypt
+            .get_recovery_request(accounts.bob)
+            .unwrap()
+            .ready_at
+            .is_some());
+
+        advance_time(60 * 60 * 24);
+        set_caller(callee, accounts.frank, 0);
+        subscrypt.finalize_recovery(accounts.bob);
+        assert!(!subscrypt.check_subscription(accounts.bob, accounts.alice, 1));
+        assert!(subscrypt.check_subscription(accounts.frank, accounts.alice, 1));
+        assert!(subscrypt.check_subscription_with_username("bob".to_string(), accounts.alice, 1));
+        assert!(subscrypt.user_check_auth(accounts.frank, "pass_phrase".to_string()));
+        assert_eq!(subscrypt.retrieve_data_with_wallet(accounts.alice).len(), 1);
+        assert!(subscrypt.get_recovery_config(accounts.frank).is_some());
+        assert!(subscrypt.get_recovery_request(accounts.bob).is_none());
+
+        assert_eq!(
+            subscrypt.get_waitlist(accounts.alice, 0),
+            (
+                vec![],
+                vec![SeatOffer {
+                    user: accounts.frank,
+                    deposit: 100,
+                    expires_at: offered_at + 60 * 60 * 24 * 2
+                }]
+            )
+        );
+        assert_eq!(
+            subscrypt.get_waitlist(accounts.alice, 2),
+            (
+                vec![WaitlistEntry {
+                    user: accounts.frank,
+                    deposit: 100
+                }],
+                vec![]
+            )
+        );
+        subscrypt.leave_waitlist(accounts.alice, 2);
+        set_caller_and_pay(callee, accounts.frank, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        assert!(subscrypt.check_subscription(accounts.frank, accounts.alice, 0));
+        assert_eq!(subscrypt.get_waitlist(accounts.alice, 0), (vec![], vec![]));
+        assert_eq!(subscrypt.get_waitlist(accounts.alice, 2), (vec![], vec![]));
+        assert!(subscrypt.audit_solvency());
+
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(
+            subscrypt.get_plan_subscribers_characteristics(accounts.alice, 1, 0, 10),
+            vec![(accounts.frank, vec!["nothing important".to_string()])]
+        );
+    }
+
+    /// Simple scenario that `eve` and `django` replace the account that `charlie` chose for the
+    /// recovery of `bob` and then `charlie` and `django` cancel the recovery
+    #[ink::test]
+    fn recovery_replace_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        let other: ink_env::AccountId = ink_env::AccountId::from([0x07; 32]);
+        set_account_balance(callee, 10100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        subscrypt.set_recovery_config(
+            vec![accounts.charlie, accounts.django, accounts.eve],
+            1,
+            60 * 60 * 24,
+        );
+
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.approve_recovery(accounts.bob, accounts.frank);
+        set_caller(callee, accounts.django, 0);
+        subscrypt.approve_recovery(accounts.bob, other);
+        assert_eq!(
+            subscrypt
+                .get_recovery_request(accounts.bob)
+                .unwrap()
+                .new_account,
+            Some(accounts.frank)
+        );
+        set_caller(callee, accounts.eve, 0);
+        subscrypt.approve_recovery(accounts.bob, other);
+        assert_eq!(
+            subscrypt
+                .get_recovery_request(accounts.bob)
+                .unwrap()
+                .new_account,
+            Some(other)
+        );
+
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.vote_cancel_recovery(accounts.bob);
+        assert!(subscrypt.get_recovery_request(accounts.bob).is_some());
+        set_caller(callee, accounts.django, 0);
+        subscrypt.vote_cancel_recovery(accounts.bob);
+        assert!(subscrypt.get_recovery_request(accounts.bob).is_none());
+    }
+
+    /// Simple scenario that guardians of `bob` approve the recovery of `bob` with a delay of
+    /// one day, so finalizing it immediately fails
+    #[ink::test]
+    #[should_panic(expected = "Recovery is not ready")]
+    fn recovery_fails_before_delay() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 10100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        subscrypt.set_recovery_config(vec![accounts.charlie], 1, 60 * 60 * 24);
+
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.approve_recovery(accounts.bob, accounts.frank);
+        subscrypt.finalize_recovery(accounts.bob);
+    }
+
+    /// Simple scenario that `bob` sets a recovery config without any delay, so guardians could
+    /// finalize in the same block as the approval and it will fail
+    #[ink::test]
+    #[should_panic(expected = "Recovery delay is too short")]
+    fn set_recovery_config_fails_zero_delay() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 10100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
         );
         set_caller(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        subscrypt.set_recovery_config(vec![accounts.charlie], 1, 0);
+    }
+    /// Simple scenario that `bob` and `charlie` subscribe to different plans of `alice` and
+    /// `bob` refunds, then `alice` lists the subscribers with different filters and pages,
+    /// then `alice` cancels `charlie` and `bob` subscribes again and both of them are still
+    /// listed once since the list keeps all-time subscribers
+    #[ink::test]
+    fn get_subscribers_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 60100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        subscrypt.refund(accounts.alice, 1);
+        set_caller(callee, accounts.charlie, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "charlie".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        set_caller(callee, accounts.alice, 0);
+        let (rows, next_cursor) = subscrypt.get_subscribers(accounts.alice, None, None, 0, 1);
+        assert_eq!(
+            rows,
+            vec![SubscriberRow {
+                user: accounts.bob,
+                plan_index: 1,
+                start: now,
+                end: now + 60 * 60 * 24 * 30,
+                status: SubscriptionStatus::Refunded
+            }]
+        );
+        assert_eq!(next_cursor, Some(1));
+        let (rows, next_cursor) = subscrypt.get_subscribers(accounts.alice, None, None, 1, 1);
+        assert_eq!(rows[0].user, accounts.charlie);
+        assert_eq!(rows[0].status, SubscriptionStatus::Active);
+        assert_eq!(next_cursor, Some(2));
+        let (rows, next_cursor) = subscrypt.get_subscribers(accounts.alice, None, None, 2, 1);
+        assert_eq!(rows.len(), 0);
+        assert_eq!(next_cursor, None);
+
+        let (rows, _) = subscrypt.get_subscribers(
+            accounts.alice,
+            None,
+            Some(SubscriptionStatus::Active),
+            0,
+            10,
+        );
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].user, accounts.charlie);
+        let (rows, _) = subscrypt.get_subscribers(accounts.alice, Some(1), None, 0, 10);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].user, accounts.bob);
+
+        subscrypt.provider_cancel_subscription(accounts.charlie, 0, 0);
+        set_account_balance(callee, 60000);
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller(callee, accounts.alice, 0);
+        let (rows, next_cursor) = subscrypt.get_subscribers(accounts.alice, None, None, 0, 10);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(next_cursor, None);
+        assert_eq!(rows[0].user, accounts.bob);
+        assert_eq!(rows[0].status, SubscriptionStatus::Active);
+        assert_eq!(rows[1].user, accounts.charlie);
+        assert_eq!(rows[1].status, SubscriptionStatus::Refunded);
+        let (rows, _) = subscrypt.get_subscribers(
+            accounts.alice,
+            None,
+            Some(SubscriptionStatus::Refunded),
+            0,
+            10,
+        );
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].user, accounts.charlie);
+        let (rows, _) = subscrypt.get_subscribers(accounts.alice, Some(1), None, 0, 10);
+        assert_eq!(rows.len(), 1);
+    }
+
+    /// Simple scenario that `charlie` who is not an operator of `alice` tries to list the
+    /// subscribers of `alice`, so it will fail
+    #[ink::test]
+    #[should_panic(expected = "You are not the provider or its operator")]
+    fn get_subscribers_fails_not_operator() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "alice".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.get_subscribers(accounts.alice, None, None, 0, 10);
+    }
+
+    /// Simple scenario that `bob` subscribes to plan 1 of `alice` and refunds it and `charlie`
+    /// subscribes to plan 0, then `alice` checks the statistics of the plans
+    #[ink::test]
+    fn plan_stats_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_account_balance(callee, 60100);
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        let refunded_amount: u128 = subscrypt.refund(accounts.alice, 1);
+        set_caller(callee, accounts.charlie, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "charlie".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        set_caller(callee, accounts.alice, 0);
+        let stats = subscrypt.get_plan_stats(accounts.alice, 1);
+        assert_eq!(stats.subscriptions, 1);
+        assert_eq!(stats.renewals, 0);
+        assert_eq!(stats.refunds, 1);
+        assert_eq!(stats.total_revenue, 50000);
+        assert_eq!(stats.refunded_volume, refunded_amount);
+        assert_eq!(stats.active_subscribers, 0);
+        assert_eq!(stats.churn, 1);
+        let stats = subscrypt.get_plan_stats(accounts.alice, 0);
+        assert_eq!(stats.subscriptions, 1);
+        assert_eq!(stats.total_revenue, 10000);
+        assert_eq!(stats.active_subscribers, 1);
+        assert_eq!(stats.churn, 0);
+
+        let history = subscrypt.get_plan_stats_history(accounts.alice, 1, 0, 40);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].0, 0);
+        assert_eq!(history[0].1.refunds, 1);
+        assert_eq!(history[0].1.expirations, 0);
+        let history = subscrypt.get_plan_stats_history(accounts.alice, 0, 0, 40);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].0, 0);
+        assert_eq!(history[0].1.subscriptions, 1);
+        assert_eq!(history[0].1.revenue, 10000);
+        assert_eq!(history[1].0, 1);
+        assert_eq!(history[1].1.expirations, 1);
+    }
+
+    /// Simple scenario that `bob` wants to see the statistics of a plan of `alice`
+    #[ink::test]
+    #[should_panic]
+    fn get_plan_stats_fails_not_operator() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.get_plan_stats(accounts.alice, 0);
+    }
+
+    /// Simple scenario that `bob` subscribes to two plans of `alice` and a plan of `charlie`,
+    /// refunds one of them and then retrieves the records page by page and with filters
+    #[ink::test]
+    fn retrieve_records_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller_and_pay(callee, accounts.charlie, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.charlie,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "charlie".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller_and_pay(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        subscrypt.refund(accounts.alice, 0);
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.charlie,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        let (records, next_cursor) = subscrypt.retrieve_records_with_wallet(
+            RecordFilter::default(),
+            RecordCursor::default(),
+            2,
+        );
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].provider, accounts.alice);
+        assert_eq!(records[0].plan_index, 1);
+        assert_eq!(records[1].plan_index, 0);
+        assert_eq!(
+            next_cursor,
+            Some(RecordCursor {
+                provider_position: 1,
+                record_index: 0
+            })
+        );
+        let (records, next_cursor) = subscrypt.retrieve_records_with_wallet(
+            RecordFilter::default(),
+            next_cursor.unwrap(),
+            2,
+        );
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].provider, accounts.charlie);
+        assert_eq!(next_cursor, None);
+
+        let (records, _) = subscrypt.retrieve_records_with_wallet(
+            RecordFilter {
+                status: Some(SubscriptionStatus::Refunded),
+                ..RecordFilter::default()
+            },
+            RecordCursor::default(),
+            10,
+        );
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].provider, accounts.alice);
+        assert_eq!(records[0].plan_index, 0);
+        let (records, _) = subscrypt.retrieve_records_with_wallet(
+            RecordFilter {
+                provider: Some(accounts.charlie),
+                ..RecordFilter::default()
+            },
+            RecordCursor::default(),
+            10,
+        );
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].provider, accounts.charlie);
+
+        let (summaries, next_cursor) = subscrypt.retrieve_record_summaries_with_wallet(
+            RecordFilter {
+                from_time: Some(60 * 60 * 24 * 2),
+                ..RecordFilter::default()
+            },
+            RecordCursor::default(),
+            10,
+        );
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        assert_eq!(
+            summaries,
+            vec![RecordSummary {
+                provider: accounts.alice,
+                plan_index: 1,
+                start: now,
+                end: now + 60 * 60 * 24 * 30,
+                status: SubscriptionStatus::Active
+            }]
+        );
+        assert_eq!(next_cursor, None);
+    }
+
+    /// Simple scenario that `bob` wants to retrieve a page of records which is too big
+    #[ink::test]
+    #[should_panic]
+    fn retrieve_records_fails_big_page() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        subscrypt.retrieve_records_with_wallet(
+            RecordFilter::default(),
+            RecordCursor::default(),
+            101,
+        );
+    }
+
+    /// Simple scenario that `bob` subscribes to plan 0 of `alice` and renews it twice, then
+    /// `alice` changes the price of the plan and disables it
+    #[ink::test]
+    fn get_subscription_status_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.renew(accounts.alice, 0, vec!["value".to_string()], 0);
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.renew(accounts.alice, 0, vec!["value".to_string()], 0);
+
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        assert_eq!(
+            subscrypt.get_subscription_status(accounts.bob, accounts.alice, 0),
+            SubscriptionInfo {
+                status: SubscriptionStatus::Active,
+                start: now,
+                end: now + 3 * 60 * 60 * 24,
+                queued_renewals: 2,
+                next_renewal_price: 10000
+            }
+        );
+
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.edit_plan(0, 60 * 60 * 24, 20000, 50, false);
+        assert_eq!(
+            subscrypt
+                .get_subscription_status(accounts.bob, accounts.alice, 0)
+                .next_renewal_price,
+            20000
+        );
+        subscrypt.change_disable(0);
+        assert_eq!(
+            subscrypt
+                .get_subscription_status(accounts.bob, accounts.alice, 0)
+                .status,
+            SubscriptionStatus::Paused
+        );
+    }
+
+    /// Simple scenario that the status of a subscription of `bob` who has never subscribed to
+    /// the plan is requested
+    #[ink::test]
+    #[should_panic]
+    fn get_subscription_status_fails_not_subscribed() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.get_subscription_status(accounts.bob, accounts.alice, 0);
+    }
+
+    /// Simple scenario that `bob` subscribes to plan 1 of `alice` and then `alice` checks the
+    /// subscriptions of `bob` and `charlie` in one call
+    #[ink::test]
+    fn check_subscriptions_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller_and_pay(callee, accounts.bob, 50000);
+        subscrypt.subscribe(
+            accounts.alice,
+            1,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(
+            subscrypt.check_subscriptions(
+                accounts.alice,
+                vec![
+                    (UserIdentifier::Address(accounts.bob), 1),
+                    (UserIdentifier::Address(accounts.bob), 0),
+                    (UserIdentifier::Username("bob".to_string()), 1),
+                    (UserIdentifier::Username("charlie".to_string()), 1),
+                    (UserIdentifier::Address(accounts.charlie), 1),
+                ]
+            ),
+            vec![true, false, true, false, false]
+        );
+        assert_eq!(
+            subscrypt.check_any_subscriptions(
+                accounts.alice,
+                vec![
+                    UserIdentifier::Username("bob".to_string()),
+                    UserIdentifier::Address(accounts.charlie),
+                ]
+            ),
+            vec![true, false]
+        );
+    }
+
+    /// Simple scenario that `alice` checks too many subscriptions in one call
+    #[ink::test]
+    #[should_panic]
+    fn check_subscriptions_fails_big_page() {
+        let subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        subscrypt.check_subscriptions(
+            accounts.alice,
+            vec![(UserIdentifier::Address(accounts.bob), 0); 101],
+        );
+    }
+
+    /// Simple scenario that `alice` and `charlie` register as providers, `alice` disables one of
+    /// the plans and then the providers and the catalog of `alice` are retrieved
+    #[ink::test]
+    fn get_catalog_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![
+                vec!["key".to_string()],
+                vec!["key".to_string(), "other".to_string()],
+            ],
+        );
+        subscrypt.change_disable(0);
+        set_caller_and_pay(callee, accounts.charlie, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.charlie,
+            vec![60 * 60 * 24],
+            vec![10000],
+            vec![50],
+            "charlie".to_string(),
+            vec![vec!["key".to_string()]],
+        );
+
+        assert_eq!(
+            subscrypt.get_providers(0, 1),
+            (vec![accounts.alice], Some(1))
+        );
+        assert_eq!(
+            subscrypt.get_providers(1, 10),
+            (vec![accounts.charlie], None)
+        );
+
+        let catalog = subscrypt.get_catalog(accounts.alice);
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog[0].plan_index, 0);
+        assert_eq!(catalog[0].enabled, false);
+        assert_eq!(catalog[0].characteristics, vec!["key".to_string()]);
+        assert_eq!(catalog[1].plan_index, 1);
+        assert_eq!(catalog[1].enabled, true);
+        assert_eq!(catalog[1].consts.price, 50000);
+        assert_eq!(catalog[1].consts.duration, 60 * 60 * 24 * 30);
+        assert_eq!(
+            catalog[1].characteristics,
+            vec!["key".to_string(), "other".to_string()]
+        );
+    }
+
+    /// Simple scenario that the catalog of `bob` who is not a provider is requested
+    #[ink::test]
+    #[should_panic]
+    fn get_catalog_fails_not_provider() {
+        let subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        subscrypt.get_catalog(accounts.bob);
+    }
+
+    /// Simple scenario that `alice` registers as a provider and sets the public profile
+    #[ink::test]
+    fn provider_profile_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        assert_eq!(
+            subscrypt.get_provider_profile(accounts.alice),
+            ProviderProfile::default()
+        );
+        let profile = ProviderProfile {
+            display_name: "Alice VPN".to_string(),
+            description: "Fast and private VPN".to_string(),
+            website: "https://alice.example".to_string(),
+            support_contact: "support@alice.example".to_string(),
+            logo_hash: [7; 32],
+            tags: vec!["vpn".to_string(), "privacy".to_string()],
+        };
+        subscrypt.set_provider_profile(profile.clone());
+        assert_eq!(subscrypt.get_provider_profile(accounts.alice), profile);
+    }
+
+    /// Simple scenario that `alice` sets a profile with a too long display name
+    #[ink::test]
+    #[should_panic]
+    fn set_provider_profile_fails_long_field() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.set_provider_profile(ProviderProfile {
+            display_name: "a".repeat(129),
+            ..ProviderProfile::default()
+        });
+    }
+
+    /// Simple scenario that `bob` subscribes to plan 0 of `alice`, then `alice` edits the plan
+    /// and `charlie` subscribes to the new version of the plan
+    #[ink::test]
+    fn plan_versions_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.edit_plan(0, 60 * 60 * 24 * 2, 15000, 100, false);
+        set_caller_and_pay(callee, accounts.charlie, 15000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "charlie".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        let (versions, next_cursor) = subscrypt.get_plan_versions(accounts.alice, 0, 0, 10);
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].consts.price, 10000);
+        assert_eq!(versions[0].changed_at, now);
+        assert_eq!(versions[1].consts.price, 15000);
+        assert_eq!(versions[1].consts.duration, 60 * 60 * 24 * 2);
+        assert_eq!(next_cursor, None);
+        let (versions, next_cursor) = subscrypt.get_plan_versions(accounts.alice, 0, 0, 1);
+        assert_eq!(versions.len(), 1);
+        assert_eq!(next_cursor, Some(1));
+
+        assert_eq!(
+            subscrypt
+                .records
+                .get(&(accounts.bob, accounts.alice))
+                .unwrap()
+                .subscription_records[0]
+                .plan_version,
+            0
+        );
+        assert_eq!(
+            subscrypt
+                .records
+                .get(&(accounts.charlie, accounts.alice))
+                .unwrap()
+                .subscription_records[0]
+                .plan_version,
+            1
+        );
+        assert_eq!(
+            subscrypt.get_plan_version(accounts.alice, 0, 1),
+            PlanVersion {
+                consts: PlanConsts {
+                    duration: 60 * 60 * 24 * 2,
+                    price: 15000,
+                    max_refund_permille_policy: 100,
+                    disabled: false
+                },
+                changed_at: now
+            }
+        );
+    }
+
+    /// Simple scenario that a version of a plan of `alice` which doesn't exist is requested
+    #[ink::test]
+    #[should_panic]
+    fn get_plan_version_fails_invalid_version() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.get_plan_version(accounts.alice, 0, 1);
+    }
+
+    /// Simple scenario that `alice` schedules price changes of plan 0 after the owner sets the
+    /// notice period to zero, `bob` subscribes before the change and `charlie` after it
+    #[ink::test]
+    fn schedule_plan_change_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.set_notice_period(0);
+        assert_eq!(subscrypt.get_notice_period(), 0);
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        let new_consts = PlanConsts {
+            duration: 60 * 60 * 24,
+            price: 20000,
+            max_refund_permille_policy: 50,
+            disabled: false,
+        };
+        subscrypt.schedule_plan_change(0, 60 * 60 * 24, 20000, 50, false, now + 100);
+        assert_eq!(
+            subscrypt.get_pending_plan_change(accounts.alice, 0),
+            Some(PlanChange {
+                consts: new_consts,
+                effective_at: now + 100
+            })
+        );
+        assert_eq!(subscrypt.get_plan_data(accounts.alice, 0).price, 10000);
+
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.schedule_plan_change(0, 60 * 60 * 24, 20000, 50, false, now);
+        assert_eq!(subscrypt.get_pending_plan_change(accounts.alice, 0), None);
+        assert_eq!(subscrypt.get_plan_data(accounts.alice, 0), new_consts);
+
+        set_caller_and_pay(callee, accounts.charlie, 20000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "charlie".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        let (versions, _) = subscrypt.get_plan_versions(accounts.alice, 0, 0, 10);
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].consts, new_consts);
+
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.schedule_plan_change(0, 60 * 60 * 24, 30000, 50, false, now + 100);
+        subscrypt.cancel_plan_change(0);
+        assert_eq!(subscrypt.get_pending_plan_change(accounts.alice, 0), None);
+    }
+
+    /// Simple scenario that `alice` schedules a change of plan 0 sooner than the notice period
+    #[ink::test]
+    #[should_panic]
+    fn schedule_plan_change_fails_notice_period() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        subscrypt.schedule_plan_change(0, 60 * 60 * 24, 20000, 50, false, now + 100);
+    }
+
+    /// Simple scenario that `alice` schedules a price change of plan 1 and disables the plan
+    /// before the change is applied, so the plan stays disabled with the new price after it
+    #[ink::test]
+    fn change_disable_keeps_pending_change_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.set_notice_period(60 * 60);
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        subscrypt.schedule_plan_change(1, 60 * 60 * 24 * 30, 80000, 100, false, now + 60 * 60);
+
+        subscrypt.change_disable(1);
+        assert!(subscrypt.get_plan_data(accounts.alice, 1).disabled);
+        assert!(
+            subscrypt
+                .get_pending_plan_change(accounts.alice, 1)
+                .unwrap()
+                .consts
+                .disabled
+        );
+
+        advance_time(60 * 60);
+        let plan: PlanConsts = subscrypt.get_plan_data(accounts.alice, 1);
+        assert_eq!(plan.price, 80000);
+        assert!(plan.disabled);
+
+        subscrypt.schedule_plan_change(1, 60 * 60 * 24 * 30, 90000, 100, true, now + 60 * 60 * 3);
+        subscrypt.edit_plan(1, 60 * 60 * 24 * 30, 80000, 100, false);
+        assert!(!subscrypt.get_plan_data(accounts.alice, 1).disabled);
+        assert!(
+            !subscrypt
+                .get_pending_plan_change(accounts.alice, 1)
+                .unwrap()
+                .consts
+                .disabled
+        );
+    }
+
+    /// Simple scenario that `alice` limits plan 0 to one subscriber, `charlie` joins the
+    /// waitlist while `bob` is subscribed and gets the seat after `bob` refunds
+    #[ink::test]
+    fn waitlist_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        let capacity = PlanCapacity {
+            max_subscribers: 1,
+            waitlist_deposit: 100,
+            claim_window: 1000,
+        };
+        subscrypt.set_plan_capacity(0, Some(capacity));
+        assert_eq!(
+            subscrypt.get_plan_capacity(accounts.alice, 0),
+            Some(capacity)
+        );
+
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller_and_pay(callee, accounts.charlie, 100);
+        subscrypt.join_waitlist(accounts.alice, 0);
+        assert_eq!(
+            subscrypt.get_waitlist(accounts.alice, 0),
+            (
+                vec![WaitlistEntry {
+                    user: accounts.charlie,
+                    deposit: 100
+                }],
+                vec![]
+            )
+        );
+        set_caller_and_pay(callee, accounts.eve, 100);
+        subscrypt.join_waitlist(accounts.alice, 0);
+        subscrypt.leave_waitlist(accounts.alice, 0);
+        assert!(subscrypt.audit_solvency());
+
+        set_caller(callee, accounts.bob, 0);
+        subscrypt.refund(accounts.alice, 0);
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        assert_eq!(
+            subscrypt.get_waitlist(accounts.alice, 0),
+            (
+                vec![],
+                vec![SeatOffer {
+                    user: accounts.charlie,
+                    deposit: 100,
+                    expires_at: now + 1000
+                }]
+            )
+        );
+
+        set_caller_and_pay(callee, accounts.charlie, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "charlie".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+        assert_eq!(subscrypt.get_waitlist(accounts.alice, 0), (vec![], vec![]));
+        assert!(subscrypt.check_subscription(accounts.charlie, accounts.alice, 0));
+        assert!(subscrypt.audit_solvency());
+    }
 
+    /// Simple scenario that `alice` limits plan 0 to one subscriber and `charlie` waits for the
+    /// seat of `bob`, the seat is offered on the day after the day that `bob` expires in and not
+    /// right after the expiry
+    #[ink::test]
+    fn waitlist_frees_seat_the_day_after_expiry_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.set_plan_capacity(
+            0,
+            Some(PlanCapacity {
+                max_subscribers: 1,
+                waitlist_deposit: 100,
+                claim_window: 60 * 60 * 24,
+            }),
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
         subscrypt.subscribe(
             accounts.alice,
             0,
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
+        set_caller_and_pay(callee, accounts.charlie, 100);
+        subscrypt.join_waitlist(accounts.alice, 0);
+
+        advance_time(60 * 60 * 24 + 10);
+        assert!(!subscrypt.check_subscription(accounts.bob, accounts.alice, 0));
+        subscrypt.process_waitlist(accounts.alice, 0);
+        let (waitlist, offers) = subscrypt.get_waitlist(accounts.alice, 0);
+        assert_eq!(waitlist.len(), 1);
+        assert!(offers.is_empty());
 
+        advance_time(60 * 60 * 24);
+        subscrypt.process_waitlist(accounts.alice, 0);
+        let (waitlist, offers) = subscrypt.get_waitlist(accounts.alice, 0);
+        assert!(waitlist.is_empty());
+        assert_eq!(offers.len(), 1);
+        assert_eq!(offers[0].user, accounts.charlie);
+    }
+
+    /// Simple scenario that `charlie` subscribes to plan 0 of `alice` which is full
+    #[ink::test]
+    #[should_panic]
+    fn subscribe_fails_plan_full() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.set_plan_capacity(
+            0,
+            Some(PlanCapacity {
+                max_subscribers: 1,
+                waitlist_deposit: 100,
+                claim_window: 1000,
+            }),
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
         subscrypt.subscribe(
             accounts.alice,
-            2,
+            0,
             [0; 32],
             "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
+        );
+        set_caller_and_pay(callee, accounts.charlie, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "charlie".to_string(),
+            vec!["nothing important".to_string()],
+            0,
+        );
+    }
+
+    /// Simple scenario that `alice` schedules the launch of plan 0, then starts a limited-time
+    /// offer and `bob` subscribes to it
+    #[ink::test]
+    fn sale_window_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        let launch = SaleWindow {
+            sale_start: Some(now + 100),
+            sale_end: None,
+            renewals: false,
+        };
+        subscrypt.set_sale_window(0, Some(launch));
+        assert_eq!(subscrypt.get_sale_window(accounts.alice, 0), Some(launch));
+        assert_eq!(subscrypt.is_on_sale(accounts.alice, 0), false);
+        assert_eq!(subscrypt.is_on_sale(accounts.alice, 1), true);
+        assert_eq!(
+            subscrypt.get_catalog(accounts.alice)[0].sale_window,
+            Some(launch)
         );
-        set_caller(callee, accounts.eve, 10000);
 
+        subscrypt.set_sale_window(
+            0,
+            Some(SaleWindow {
+                sale_start: None,
+                sale_end: Some(now + 100),
+                renewals: false,
+            }),
+        );
+        assert_eq!(subscrypt.is_on_sale(accounts.alice, 0), true);
+        set_caller_and_pay(callee, accounts.bob, 10000);
         subscrypt.subscribe(
             accounts.alice,
             0,
             [0; 32],
-            "eve".to_string(),
+            "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
         );
+        assert!(subscrypt.check_subscription(accounts.bob, accounts.alice, 0));
+
+        set_caller(callee, accounts.alice, 0);
+        subscrypt.set_sale_window(0, None);
+        assert_eq!(subscrypt.get_sale_window(accounts.alice, 0), None);
+    }
+
+    /// Simple scenario that `bob` subscribes to plan 0 of `alice` before its sale window
+    #[ink::test]
+    #[should_panic]
+    fn subscribe_fails_not_on_sale() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        let now: u64 = ink_env::block_timestamp::<ink_env::DefaultEnvironment>().unwrap();
+        subscrypt.set_sale_window(
+            0,
+            Some(SaleWindow {
+                sale_start: Some(now + 100),
+                sale_end: None,
+                renewals: false,
+            }),
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
         subscrypt.subscribe(
             accounts.alice,
-            3,
+            0,
             [0; 32],
-            "eve".to_string(),
+            "bob".to_string(),
             vec!["nothing important".to_string()],
+            0,
+        );
+    }
+
+    /// Simple scenario that `alice` adds a typed characteristic to plan 0, deprecates the old
+    /// one and `bob` subscribes with values that match the schema, then `alice` publishes an
+    /// encryption key and `charlie` subscribes with an encrypted value which is longer than the
+    /// plaintext limit
+    #[ink::test]
+    fn characteristic_schema_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
         );
+        subscrypt.add_characteristic_for_plan(0, vec!["age".to_string()]);
+        let age_rules = CharacteristicRules {
+            value_type: CharacteristicType::Number,
+            required: true,
+            max_length: Some(3),
+            deprecated: false,
+        };
+        subscrypt.edit_characteristic(0, 1, "years".to_string(), age_rules);
+        let deprecated_rules = CharacteristicRules {
+            deprecated: true,
+            ..CharacteristicRules::default()
+        };
+        subscrypt.edit_characteristic(0, 0, "key".to_string(), deprecated_rules);
         assert_eq!(
-            subscrypt
-                .users
-                .get(&accounts.bob)
-                .unwrap()
-                .list_of_providers
-                .get(0)
-                .unwrap(),
-            &accounts.alice
+            subscrypt.get_characteristic_schema(accounts.alice, 0),
+            vec![
+                Characteristic {
+                    key: "key".to_string(),
+                    rules: deprecated_rules
+                },
+                Characteristic {
+                    key: "years".to_string(),
+                    rules: age_rules
+                }
+            ]
+        );
+        assert_eq!(
+            subscrypt.get_characteristic_schema(accounts.alice, 1),
+            vec![Characteristic {
+                key: "key".to_string(),
+                rules: CharacteristicRules::default()
+            }]
+        );
+
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["".to_string(), "30".to_string()],
+            0,
+        );
+        assert!(subscrypt.check_subscription(accounts.bob, accounts.alice, 0));
+
+        set_caller(callee, accounts.alice, 0);
+        assert_eq!(subscrypt.publish_encryption_key(vec![1, 2, 3]), 1);
+        set_caller_and_pay(callee, accounts.charlie, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "charlie".to_string(),
+            vec!["".to_string(), "0x8f3a91c4d2e7".to_string()],
+            1,
+        );
+        assert!(subscrypt.check_subscription(accounts.charlie, accounts.alice, 0));
+    }
+
+    /// Simple scenario that `bob` subscribes with a value that doesn't match the characteristic
+    /// type
+    #[ink::test]
+    #[should_panic]
+    fn subscribe_fails_invalid_characteristic_type() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        subscrypt.edit_characteristic(
+            0,
+            0,
+            "age".to_string(),
+            CharacteristicRules {
+                value_type: CharacteristicType::Number,
+                ..CharacteristicRules::default()
+            },
+        );
+        set_caller_and_pay(callee, accounts.bob, 10000);
+        subscrypt.subscribe(
+            accounts.alice,
+            0,
+            [0; 32],
+            "bob".to_string(),
+            vec!["thirty".to_string()],
+            0,
+        );
+    }
+
+    /// Simple scenario that `alice` and `charlie` sell their plans in a bundle and `bob` buys
+    /// and refunds the bundle
+    #[ink::test]
+    fn bundle_works() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller_and_pay(callee, accounts.charlie, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.charlie,
+            vec![60 * 60 * 24 * 30],
+            vec![30000],
+            vec![50],
+            "charlie".to_string(),
+            vec![vec!["key".to_string()]],
         );
+
         set_caller(callee, accounts.alice, 0);
+        let bundle_id: u32 = subscrypt.create_bundle(
+            vec![(accounts.alice, 1), (accounts.charlie, 0)],
+            vec![600, 400],
+            40001,
+        );
+        assert_eq!(bundle_id, 0);
+        assert_eq!(subscrypt.get_bundle(bundle_id).is_approved(), false);
+        set_caller(callee, accounts.charlie, 0);
+        subscrypt.approve_bundle(bundle_id);
+        let bundle: Bundle = subscrypt.get_bundle(bundle_id);
+        assert_eq!(bundle.creator, accounts.alice);
+        assert_eq!(bundle.is_approved(), true);
+        assert_eq!(subscrypt.get_bundles(0, 10), (vec![bundle], None));
+
+        set_caller_and_pay(callee, accounts.bob, 40001);
+        subscrypt.subscribe_bundle(
+            bundle_id,
+            [0; 32],
+            "bob".to_string(),
+            vec![
+                vec!["nothing important".to_string()],
+                vec!["nothing important".to_string()],
+            ],
+            vec![0, 0],
+        );
+        assert!(subscrypt.check_subscription(accounts.bob, accounts.alice, 1));
+        assert!(subscrypt.check_subscription(accounts.bob, accounts.charlie, 0));
+        let record = &subscrypt
+            .records
+            .get(&(accounts.bob, accounts.charlie))
+            .unwrap()
+            .subscription_records[0];
+        assert_eq!(record.bundle_id, Some(bundle_id));
+        assert_eq!(record.plan.price, 16000);
+
+        assert_eq!(subscrypt.refund_bundle(bundle_id), 2400 + 800);
+        assert!(!subscrypt.check_subscription(accounts.bob, accounts.alice, 1));
+        assert!(!subscrypt.check_subscription(accounts.bob, accounts.charlie, 0));
+    }
+
+    /// Simple scenario that `bob` buys a bundle that is not approved by `charlie`
+    #[ink::test]
+    #[should_panic]
+    fn subscribe_bundle_fails_not_approved() {
+        let mut subscrypt = Subscrypt::new();
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+            .expect("Cannot get accounts");
+        let callee =
+            ink_env::test::get_current_contract_account_id::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get contract id");
+        set_caller_and_pay(callee, accounts.alice, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.alice,
+            vec![60 * 60 * 24, 60 * 60 * 24 * 30],
+            vec![10000, 50000],
+            vec![50, 100],
+            "alice".to_string(),
+            vec![vec!["key".to_string()], vec!["key".to_string()]],
+        );
+        set_caller_and_pay(callee, accounts.charlie, 100);
+        subscrypt_provider_register_routine(
+            &mut subscrypt,
+            accounts.charlie,
+            vec![60 * 60 * 24 * 30],
+            vec![30000],
+            vec![50],
+            "charlie".to_string(),
+            vec![vec!["key".to_string()]],
+        );
 
-        subscrypt.process(accounts.alice, 1000);
+        set_caller(callee, accounts.alice, 0);
+        let bundle_id: u32 = subscrypt.create_bundle(
+            vec![(accounts.alice, 1), (accounts.charlie, 0)],
+            vec![600, 400],
+            40000,
+        );
+        set_caller_and_pay(callee, accounts.bob, 40000);
+        subscrypt.subscribe_bundle(
+            bundle_id,
+            [0; 32],
+            "bob".to_string(),
+            vec![
+                vec!["nothing important".to_string()],
+                vec!["nothing important".to_string()],
+            ],
+            vec![0, 0],
+        );
     }
 }
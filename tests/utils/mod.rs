@@ -2,10 +2,11 @@
 
 pub mod utils {
     use crate::subscrypt::subscrypt::Subscrypt;
+    use ink_env::hash::{Blake2x256, HashOutput, Sha2x256};
     use ink_env::AccountId as Account;
     use ink_env::{call, test};
+    use libsecp256k1_core::curve::{Affine, ECMultGenContext, Jacobian, Scalar};
     const DEFAULT_GAS_LIMIT: u64 = 1_000_000;
-    use ink_env::hash::{HashOutput, Sha2x256};
 
     /// This function will set the `caller` and `callee` of transaction with endowment amount of
     /// `value`
@@ -18,12 +19,66 @@ pub mod utils {
             test::CallData::new(call::Selector::new([0x00; 4])),
         );
     }
+    /// This function will set the `caller` and `callee` of transaction with endowment amount of
+    /// `value` and also adds `value` to the balance of `callee` like a real payable call
+    pub fn set_caller_and_pay(callee: Account, from: Account, value: u128) {
+        let balance: u128 =
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(callee)
+                .expect("Cannot get account balance");
+        set_account_balance(callee, balance + value);
+        set_caller(callee, from, value);
+    }
+
+    /// This function will advance the block timestamp by at least `seconds` by adding new blocks
+    /// of the off-chain environment which are 5 seconds apart
+    pub fn advance_time(seconds: u64) {
+        for _ in 0..(seconds + 4) / 5 {
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+        }
+    }
+
     /// This function will set the account balance of `callee` to `value`
     pub fn set_account_balance(account: Account, value: u128) {
         ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(account, value)
             .expect("Cannot set account balance");
     }
 
+    /// This function will return the 33 bytes compressed ECDSA public key of `secret_key`
+    pub fn login_public_key(secret_key: [u8; 32]) -> Vec<u8> {
+        let mut secret = Scalar::default();
+        let _ = secret.set_b32(&secret_key);
+        let mut point = Jacobian::default();
+        ECMultGenContext::new_boxed().ecmult_gen(&mut point, &secret);
+        let mut public_key = Affine::default();
+        public_key.set_gej(&point);
+        public_key.x.normalize();
+        public_key.y.normalize();
+        let mut compressed = vec![if public_key.y.is_odd() { 3 } else { 2 }];
+        compressed.extend_from_slice(&public_key.x.b32());
+        compressed
+    }
+
+    /// This function will sign `message_hash` with `secret_key` and return the 65 bytes
+    /// recoverable signature like a wallet does for the login challenges
+    pub fn sign_login_message(secret_key: [u8; 32], message_hash: [u8; 32]) -> Vec<u8> {
+        let mut nonce_bytes = <Blake2x256 as HashOutput>::Type::default();
+        ink_env::hash_encoded::<Blake2x256, _>(&(secret_key, message_hash), &mut nonce_bytes);
+        let mut secret = Scalar::default();
+        let mut message = Scalar::default();
+        let mut nonce = Scalar::default();
+        let _ = secret.set_b32(&secret_key);
+        let _ = message.set_b32(&message_hash);
+        let _ = nonce.set_b32(&nonce_bytes);
+        let (sig_r, sig_s, recovery_id) = ECMultGenContext::new_boxed()
+            .sign_raw(&secret, &message, &nonce)
+            .expect("Cannot sign the message");
+        let mut signature = sig_r.b32().to_vec();
+        signature.extend_from_slice(&sig_s.b32());
+        signature.push(recovery_id);
+        signature
+    }
+
     /// This function will do the provider registration routines
     pub fn subscrypt_provider_register_routine(
         subscrypt: &mut Subscrypt,